@@ -0,0 +1,40 @@
+use anyhow::{bail, Result};
+use reqwest::{redirect::Policy, Client};
+use std::fs;
+use transcribe::catalog::CatalogEntry;
+use transcribe::model_handler;
+
+// Pins the exact size and the SHA-256 of every entry of catalog.json, as Hugging Face
+// announces them: `cargo run --example catalog_checksums`. A pinned checksum that no
+// longer matches is reported rather than replaced, so that a changed model is reviewed.
+#[tokio::main]
+async fn main() -> Result<()> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/catalog.json");
+    let mut entries: Vec<CatalogEntry> = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let client = Client::builder().redirect(Policy::none()).build()?;
+
+    for entry in entries.iter_mut() {
+        let checksum = match model_handler::announced_checksum(&client, &entry.url).await {
+            Some(checksum) => checksum,
+            None => bail!("no checksum announced for {}", entry.url),
+        };
+
+        if let Some(pinned) = &entry.checksum {
+            if *pinned != checksum {
+                bail!(
+                    "{} changed: pinned sha256 {}, announced {}",
+                    entry.name,
+                    pinned.sha256,
+                    checksum.sha256
+                );
+            }
+        }
+
+        println!("{}\t{}\t{}", entry.name, checksum.size, checksum.sha256);
+        entry.size_mb = (checksum.size + (1 << 19)) >> 20;
+        entry.checksum = Some(checksum);
+    }
+
+    fs::write(path, serde_json::to_string_pretty(&entries)? + "\n")?;
+    Ok(())
+}
@@ -1,19 +1,30 @@
 use anyhow::Result;
 use transcribe::model_handler;
+use transcribe::options::TranscribeOptions;
 use transcribe::transcriber;
 
 // "ggml-tiny.bin", "ggml-base.bin", "ggml-small.bin", "ggml-medium.bin", "ggml-large.bin",
 #[tokio::main]
 async fn main() -> Result<()> {
     let m = model_handler::ModelHandler::new("ggml-tiny.bin", "models")?;
-    m.download_model(None).await?;
-    // m.download_model(Some(("127.0.0.1", 1084))).await?;
+    m.download_model(&model_handler::DownloadOptions::default())
+        .await?;
+    // m.download_model(&model_handler::DownloadOptions {
+    //     proxy: Some(model_handler::ProxyOptions {
+    //         url: "socks5h://127.0.0.1:1084".to_string(),
+    //         ..Default::default()
+    //     }),
+    //     ..Default::default()
+    // })
+    // .await?;
     let trans = transcriber::Transcriber::new(m)?;
-    let result = trans.transcribe("src/test_data/test.mp3", None)?;
-    let text = result.get_text();
-    let start = result.get_start_timestamp();
-    let end = result.get_end_timestamp();
-    println!("start[{}]-end[{}] {}", start, end, text);
+    let result = trans.transcribe("src/test_data/test.mp3", &TranscribeOptions::default())?;
+    for segment in result.get_segments() {
+        println!(
+            "start[{}]-end[{}] {}",
+            segment.start_timestamp, segment.end_timestamp, segment.text
+        );
+    }
 
     Ok(())
 }
@@ -1,16 +1,316 @@
 use anyhow::{anyhow, Result};
+use rubato::{FftFixedIn, Resampler};
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::path::Path;
 
 use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
 use symphonia::core::errors::Error;
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::formats::{FormatOptions, FormatReader, Track};
 use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
-use symphonia::core::probe::Hint;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision};
+use symphonia::core::probe::{Hint, ProbeResult};
 
-pub fn parse_audio_file(audio_path: &str) -> Result<Vec<f32>> {
+/// Sample rate expected by whisper.
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+/// Chunk size used by `parse_audio_track`, one second of whisper audio.
+pub const STREAM_CHUNK_SIZE: usize = WHISPER_SAMPLE_RATE as usize;
+
+const RESAMPLER_CHUNK_SIZE: usize = 1024;
+
+/// What `parse_audio_file` had to do to turn the source into 16 kHz mono.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioConversion {
+    pub source_sample_rate: u32,
+    pub source_channels: usize,
+    pub downmixed: bool,
+    pub resampled: bool,
+}
+
+/// Which track of a container to decode.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TrackSelector {
+    /// The default track if it is decodable audio, otherwise the first decodable audio track.
+    #[default]
+    Auto,
+    /// The track at this position in the container, counting video tracks too.
+    Index(usize),
+    /// The first decodable audio track with this language tag, e.g. "eng".
+    Language(String),
+}
+
+/// Stream details reported by `probe_audio`. Fields the container does not declare are `None`.
+#[derive(Debug, Clone, Default)]
+pub struct AudioInfo {
+    pub duration_ms: Option<u64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<usize>,
+    pub bits_per_sample: Option<u32>,
+    pub codec: String,
+    pub track_id: u32,
+    pub track_count: usize,
+    pub tags: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodedAudio {
+    /// 16 kHz mono samples.
+    pub samples: Vec<f32>,
+    pub conversion: AudioConversion,
+}
+
+/// File extensions of the formats enabled through the crate features.
+pub fn supported_extensions() -> Vec<&'static str> {
+    let mut extensions = vec![];
+
+    if cfg!(feature = "mp3") {
+        extensions.push("mp3");
+    }
+
+    if cfg!(feature = "wav") {
+        extensions.extend(["wav", "wave"]);
+    }
+
+    if cfg!(feature = "flac") {
+        extensions.push("flac");
+    }
+
+    if cfg!(feature = "ogg") {
+        extensions.extend(["ogg", "oga"]);
+    }
+
+    if cfg!(feature = "aac") {
+        extensions.push("aac");
+    }
+
+    if cfg!(any(feature = "aac", feature = "alac")) {
+        extensions.push("m4a");
+    }
+
+    if cfg!(feature = "mp4") {
+        extensions.extend(["mp4", "m4v", "mov"]);
+    }
+
+    // Not webm, its audio is mostly Opus, which symphonia does not decode
+    if cfg!(feature = "mkv") {
+        extensions.extend(["mkv", "mka"]);
+    }
+
+    extensions
+}
+
+pub fn parse_audio_file(audio_path: &str) -> Result<DecodedAudio> {
+    parse_audio_track(audio_path, &TrackSelector::Auto)
+}
+
+/// Decodes the selected track of an audio or video file into memory. Use `AudioStream` for
+/// long recordings.
+pub fn parse_audio_track(audio_path: &str, selector: &TrackSelector) -> Result<DecodedAudio> {
+    let mut stream = AudioStream::open(audio_path, selector, STREAM_CHUNK_SIZE)?;
+
+    let mut audio_data: Vec<f32> = vec![];
+    for chunk in stream.by_ref() {
+        audio_data.extend_from_slice(&chunk?);
+    }
+
+    let conversion = match stream.conversion() {
+        Some(v) => v,
+        _ => return Err(anyhow!("no audio decoded")),
+    };
+
+    Ok(DecodedAudio {
+        samples: audio_data,
+        conversion,
+    })
+}
+
+/// Lazily decodes a track and yields 16 kHz mono chunks of `chunk_size` samples, only the last
+/// chunk may be shorter. Memory use does not depend on the length of the file.
+pub struct AudioStream {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_buf: Option<SampleBuffer<f32>>,
+    converter: Option<Converter>,
+    conversion: Option<AudioConversion>,
+    buffer: Vec<f32>,
+    chunk_size: usize,
+    is_finished: bool,
+}
+
+impl AudioStream {
+    pub fn open(audio_path: &str, selector: &TrackSelector, chunk_size: usize) -> Result<Self> {
+        if chunk_size == 0 {
+            return Err(anyhow!("chunk size must not be 0"));
+        }
+
+        // Use the default options when decoding.
+        let decoder_opts: DecoderOptions = Default::default();
+
+        // Get the format reader yielded by the probe operation.
+        let format = probe_format(audio_path)?.format;
+
+        // Get the audio track, video containers may have a video track as the default track.
+        let track = select_track(format.as_ref(), selector)?;
+
+        // Create a decoder for the track.
+        let decoder = symphonia::default::get_codecs().make(&track.codec_params, &decoder_opts)?;
+
+        // Store the track identifier, we'll use it to filter packets.
+        let track_id = track.id;
+
+        Ok(AudioStream {
+            format,
+            decoder,
+            track_id,
+            sample_buf: None,
+            converter: None,
+            conversion: None,
+            buffer: Vec::with_capacity(chunk_size),
+            chunk_size,
+            is_finished: false,
+        })
+    }
+
+    /// Known once the first packet has been decoded.
+    pub fn conversion(&self) -> Option<AudioConversion> {
+        self.conversion
+    }
+
+    /// Decodes packets until a chunk is buffered. Returns false at the end of the track.
+    fn fill(&mut self) -> Result<bool> {
+        while self.buffer.len() < self.chunk_size {
+            // Get the next packet from the format reader.
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(_)) => return Ok(false),
+                Err(err) => return Err(anyhow!("{}", err)),
+            };
+            // If the packet does not belong to the selected track, skip it.
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            // Decode the packet into audio samples, ignoring any decode errors.
+            match self.decoder.decode(&packet) {
+                Ok(audio_buf) => {
+                    // If this is the *first* decoded packet, create a sample buffer and a
+                    // converter matching the decoded audio buffer format.
+                    if self.sample_buf.is_none() {
+                        // Get the audio buffer specification.
+                        let spec = *audio_buf.spec();
+
+                        // Get the capacity of the decoded buffer. Note: This is capacity, not length!
+                        let duration = audio_buf.capacity() as u64;
+
+                        // Create the f32 sample buffer.
+                        self.sample_buf = Some(SampleBuffer::<f32>::new(duration, spec));
+
+                        let converter = Converter::new(spec.rate, spec.channels.count())?;
+                        self.conversion = Some(converter.conversion());
+                        self.converter = Some(converter);
+                    }
+
+                    if let (Some(buf), Some(converter)) =
+                        (&mut self.sample_buf, &mut self.converter)
+                    {
+                        // Copy the samples in an interleaved order while converting them to f32.
+                        buf.copy_interleaved_ref(audio_buf);
+                        converter.push(buf.samples(), &mut self.buffer)?;
+                    }
+                }
+                Err(Error::DecodeError(_)) => (),
+                Err(_) => return Ok(false),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl Iterator for AudioStream {
+    type Item = Result<Vec<f32>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.is_finished {
+            match self.fill() {
+                Ok(true) => (),
+                Ok(false) => {
+                    self.is_finished = true;
+                    if let Some(converter) = self.converter.take() {
+                        if let Err(e) = converter.finish(&mut self.buffer) {
+                            return Some(Err(e));
+                        }
+                    }
+                }
+                Err(e) => {
+                    self.is_finished = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        let len = self.chunk_size.min(self.buffer.len());
+        Some(Ok(self.buffer.drain(..len).collect()))
+    }
+}
+
+/// Reads the container header and metadata of an audio or video file without decoding it.
+pub fn probe_audio(audio_path: &str) -> Result<AudioInfo> {
+    let mut probed = probe_format(audio_path)?;
+    let track_count = probed.format.tracks().len();
+    let track = select_track(probed.format.as_ref(), &TrackSelector::Auto)?;
+    let (track_id, params) = (track.id, track.codec_params.clone());
+
+    let duration_ms = match (params.time_base, params.n_frames) {
+        (Some(time_base), Some(n_frames)) => {
+            let time = time_base.calc_time(n_frames);
+            Some(time.seconds * 1000 + (time.frac * 1000.0) as u64)
+        }
+        (None, Some(n_frames)) => params.sample_rate.map(|rate| n_frames * 1000 / rate as u64),
+        _ => None,
+    };
+
+    let codec = symphonia::default::get_codecs()
+        .get_codec(params.codec)
+        .map(|descriptor| descriptor.short_name.to_string())
+        .unwrap_or_default();
+
+    // Tags can live in the container itself or in a header that was read while probing, e.g. ID3.
+    let mut tags = vec![];
+    let mut push_tags = |revision: Option<&MetadataRevision>| {
+        for tag in revision.map(|r| r.tags()).unwrap_or_default() {
+            let key = match tag.std_key {
+                Some(key) => format!("{key:?}"),
+                _ => tag.key.clone(),
+            };
+            tags.push((key, tag.value.to_string()));
+        }
+    };
+    push_tags(probed.format.metadata().current());
+    if let Some(metadata) = probed.metadata.get() {
+        push_tags(metadata.current());
+    }
+
+    Ok(AudioInfo {
+        duration_ms,
+        sample_rate: params.sample_rate,
+        channels: params.channels.map(|c| c.count()),
+        bits_per_sample: params.bits_per_sample,
+        codec,
+        track_id,
+        track_count,
+        tags,
+    })
+}
+
+fn probe_format(audio_path: &str) -> Result<ProbeResult> {
     // Create a media source. Note that the MediaSource trait is automatically implemented for File,
     // among other types.
     let file = Box::new(File::open(Path::new(&audio_path))?);
@@ -18,84 +318,342 @@ pub fn parse_audio_file(audio_path: &str) -> Result<Vec<f32>> {
     // Create the media source stream using the boxed media source from above.
     let mss = MediaSourceStream::new(file, Default::default());
 
-    // Create a hint to help the format registry guess what format reader is appropriate. In this
-    // example we'll leave it empty.
-    let hint = Hint::new();
+    // Create a hint to help the format registry guess what format reader is appropriate.
+    let mut hint = Hint::new();
+    if let Some(ext) = Path::new(audio_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        hint.with_extension(ext);
+    }
 
-    // Use the default options when reading and decoding.
+    // Use the default options when reading.
     let format_opts: FormatOptions = Default::default();
     let metadata_opts: MetadataOptions = Default::default();
-    let decoder_opts: DecoderOptions = Default::default();
 
     // Probe the media source stream for a format.
-    let probed =
-        symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
+    Ok(symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts)?)
+}
 
-    // Get the format reader yielded by the probe operation.
-    let mut format = probed.format;
+fn select_track<'a>(format: &'a dyn FormatReader, selector: &TrackSelector) -> Result<&'a Track> {
+    let tracks = format.tracks();
 
-    // Get the default track.
-    let track = match format.default_track() {
-        Some(v) => v,
-        _ => return Err(anyhow!("get default track failed")),
-    };
+    match selector {
+        TrackSelector::Auto => format
+            .default_track()
+            .filter(|track| is_decodable_audio(track))
+            .or_else(|| tracks.iter().find(|track| is_decodable_audio(track)))
+            .ok_or_else(|| anyhow!("no decodable audio track")),
+        TrackSelector::Index(index) => match tracks.get(*index) {
+            Some(track) if is_decodable_audio(track) => Ok(track),
+            Some(_) => Err(anyhow!("track {index} is not a decodable audio track")),
+            _ => Err(anyhow!(
+                "track {index} does not exist, the file has {} tracks",
+                tracks.len()
+            )),
+        },
+        TrackSelector::Language(language) => tracks
+            .iter()
+            .filter(|track| is_decodable_audio(track))
+            .find(|track| {
+                track
+                    .language
+                    .as_ref()
+                    .is_some_and(|l| l.eq_ignore_ascii_case(language))
+            })
+            .ok_or_else(|| anyhow!("no decodable audio track with language {language}")),
+    }
+}
 
-    // Create a decoder for the track.
-    let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &decoder_opts)?;
+fn is_decodable_audio(track: &Track) -> bool {
+    track.codec_params.codec != CODEC_TYPE_NULL
+        && track.codec_params.sample_rate.is_some()
+        && symphonia::default::get_codecs()
+            .get_codec(track.codec_params.codec)
+            .is_some()
+}
 
-    // Store the track identifier, we'll use it to filter packets.
-    let track_id = track.id;
+/// Downmixes interleaved frames to mono and resamples them to `WHISPER_SAMPLE_RATE`.
+struct Converter {
+    channels: usize,
+    source_sample_rate: u32,
+    resampler: Option<MonoResampler>,
+}
 
-    let mut sample_buf = None;
+impl Converter {
+    fn new(source_sample_rate: u32, channels: usize) -> Result<Converter> {
+        if channels == 0 {
+            return Err(anyhow!("audio without channels"));
+        }
 
-    let mut audio_data: Vec<f32> = vec![];
-    loop {
-        // Get the next packet from the format reader.
-        let packet = match format.next_packet() {
-            Ok(packet) => packet,
-            Err(Error::IoError(_)) => break,
-            Err(err) => return Err(anyhow!("{}", err)),
+        let resampler = if source_sample_rate != WHISPER_SAMPLE_RATE {
+            Some(MonoResampler::new(source_sample_rate)?)
+        } else {
+            None
         };
-        // If the packet does not belong to the selected track, skip it.
-        if packet.track_id() != track_id {
-            continue;
-        }
 
-        // Decode the packet into audio samples, ignoring any decode errors.
-        match decoder.decode(&packet) {
-            Ok(audio_buf) => {
-                // The decoded audio samples may now be accessed via the audio buffer if per-channel
-                // slices of samples in their native decoded format is desired. Use-cases where
-                // the samples need to be accessed in an interleaved order or converted into
-                // another sample format, or a byte buffer is required, are covered by copying the
-                // audio buffer into a sample buffer or raw sample buffer, respectively. In the
-                // example below, we will copy the audio buffer into a sample buffer in an
-                // interleaved order while also converting to a f32 sample format.
-
-                // If this is the *first* decoded packet, create a sample buffer matching the
-                // decoded audio buffer format.
-                if sample_buf.is_none() {
-                    // Get the audio buffer specification.
-                    let spec = *audio_buf.spec();
-
-                    // Get the capacity of the decoded buffer. Note: This is capacity, not length!
-                    let duration = audio_buf.capacity() as u64;
-
-                    // Create the f32 sample buffer.
-                    sample_buf = Some(SampleBuffer::<f32>::new(duration, spec));
-                }
+        Ok(Converter {
+            channels,
+            source_sample_rate,
+            resampler,
+        })
+    }
 
-                if let Some(buf) = &mut sample_buf {
-                    buf.copy_interleaved_ref(audio_buf);
+    fn conversion(&self) -> AudioConversion {
+        AudioConversion {
+            source_sample_rate: self.source_sample_rate,
+            source_channels: self.channels,
+            downmixed: self.channels > 1,
+            resampled: self.resampler.is_some(),
+        }
+    }
 
-                    // The samples may now be access via the `samples()` function.
-                    audio_data.append(&mut buf.samples().to_vec());
-                }
+    fn push(&mut self, interleaved: &[f32], out: &mut Vec<f32>) -> Result<()> {
+        match &mut self.resampler {
+            Some(resampler) => resampler.push_interleaved(interleaved, self.channels, out),
+            None => {
+                downmix_into(interleaved, self.channels, out);
+                Ok(())
             }
-            Err(Error::DecodeError(_)) => (),
-            Err(_) => break,
         }
     }
 
-    Ok(audio_data)
+    fn finish(self, out: &mut Vec<f32>) -> Result<()> {
+        match self.resampler {
+            Some(resampler) => resampler.finish(out),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Length of `ms` milliseconds of whisper audio, in samples.
+pub(crate) fn ms_to_samples(ms: u64) -> usize {
+    (ms * WHISPER_SAMPLE_RATE as u64 / 1000) as usize
+}
+
+/// Length of `samples` samples of whisper audio, in milliseconds.
+pub(crate) fn samples_to_ms(samples: usize) -> i64 {
+    (samples as u64 * 1000 / WHISPER_SAMPLE_RATE as u64) as i64
+}
+
+/// Averages every frame of `channels` interleaved samples into a single sample.
+fn downmix_into(interleaved: &[f32], channels: usize, out: &mut Vec<f32>) {
+    if channels == 1 {
+        out.extend_from_slice(interleaved);
+        return;
+    }
+
+    out.extend(
+        interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+    );
+}
+
+/// Band-limited FFT resampler that accepts input of any length and compensates for the
+/// resampler delay, so the output lines up with the input timeline.
+struct MonoResampler {
+    resampler: FftFixedIn<f32>,
+    source_sample_rate: u32,
+    pending: Vec<f32>,
+    skip: usize,
+    input_frames: usize,
+    output_frames: usize,
+}
+
+impl MonoResampler {
+    fn new(source_sample_rate: u32) -> Result<MonoResampler> {
+        let resampler = FftFixedIn::<f32>::new(
+            source_sample_rate as usize,
+            WHISPER_SAMPLE_RATE as usize,
+            RESAMPLER_CHUNK_SIZE,
+            2,
+            1,
+        )
+        .map_err(|e| anyhow!("create resampler failed: {e}"))?;
+
+        Ok(MonoResampler {
+            skip: resampler.output_delay(),
+            resampler,
+            source_sample_rate,
+            pending: vec![],
+            input_frames: 0,
+            output_frames: 0,
+        })
+    }
+
+    /// Downmixes `interleaved` and resamples every full chunk, the rest is kept for later.
+    fn push_interleaved(
+        &mut self,
+        interleaved: &[f32],
+        channels: usize,
+        out: &mut Vec<f32>,
+    ) -> Result<()> {
+        let len = self.pending.len();
+        downmix_into(interleaved, channels, &mut self.pending);
+        self.input_frames += self.pending.len() - len;
+        self.process_pending(out)
+    }
+
+    fn process_pending(&mut self, out: &mut Vec<f32>) -> Result<()> {
+        let mut offset = 0;
+        while self.pending.len() - offset >= self.resampler.input_frames_next() {
+            let frames = self.resampler.input_frames_next();
+            let output = self
+                .resampler
+                .process(&[&self.pending[offset..offset + frames]], None)
+                .map_err(|e| anyhow!("resample failed: {e}"))?;
+            offset += frames;
+            self.emit(&output[0], out);
+        }
+        self.pending.drain(..offset);
+
+        Ok(())
+    }
+
+    fn finish(mut self, out: &mut Vec<f32>) -> Result<()> {
+        let expected = (self.input_frames as u64 * WHISPER_SAMPLE_RATE as u64)
+            .div_ceil(self.source_sample_rate as u64) as usize;
+
+        let pending = std::mem::take(&mut self.pending);
+        let mut input = Some(&[pending.as_slice()][..]);
+
+        // Flush the remaining input and then the frames held back by the resampler delay.
+        while self.output_frames < expected {
+            let output = self
+                .resampler
+                .process_partial(input.take(), None)
+                .map_err(|e| anyhow!("resample failed: {e}"))?;
+            self.emit(&output[0], out);
+        }
+
+        let surplus = self.output_frames - expected;
+        out.truncate(out.len() - surplus);
+        Ok(())
+    }
+
+    fn emit(&mut self, samples: &[f32], out: &mut Vec<f32>) {
+        let skip = self.skip.min(samples.len());
+        self.skip -= skip;
+        self.output_frames += samples.len() - skip;
+        out.extend_from_slice(&samples[skip..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downmix() {
+        let mut out = vec![];
+        downmix_into(&[1.0, 3.0, -1.0, 1.0], 2, &mut out);
+        assert_eq!(out, vec![2.0, 0.0]);
+
+        out.clear();
+        downmix_into(&[0.5, 0.25], 1, &mut out);
+        assert_eq!(out, vec![0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_resample_length_and_alignment() -> Result<()> {
+        let source_rate = 44100;
+        let tone = |rate: u32, n: usize| -> Vec<f32> {
+            (0..n)
+                .map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / rate as f32).sin())
+                .collect()
+        };
+
+        let input = tone(source_rate, source_rate as usize);
+        let mut converter = Converter::new(source_rate, 1)?;
+        let mut output = vec![];
+        for chunk in input.chunks(1000) {
+            converter.push(chunk, &mut output)?;
+        }
+        converter.finish(&mut output)?;
+
+        assert_eq!(output.len(), WHISPER_SAMPLE_RATE as usize);
+
+        let expected = tone(WHISPER_SAMPLE_RATE, output.len());
+        let middle = output.len() / 4..output.len() * 3 / 4;
+        let max_error = output[middle.clone()]
+            .iter()
+            .zip(&expected[middle])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        assert!(max_error < 0.01, "max error {max_error}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_supported_extensions() {
+        let extensions = supported_extensions();
+        assert_eq!(extensions.contains(&"mp3"), cfg!(feature = "mp3"));
+        assert_eq!(extensions.contains(&"flac"), cfg!(feature = "flac"));
+        assert_eq!(
+            extensions.contains(&"m4a"),
+            cfg!(any(feature = "aac", feature = "alac"))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "mp3")]
+    fn test_parse_audio_file() -> Result<()> {
+        let audio = parse_audio_file("src/test_data/test.mp3")?;
+        assert!(!audio.samples.is_empty());
+        assert!(audio.samples.iter().all(|s| s.is_finite()));
+        assert_eq!(
+            audio.conversion.resampled,
+            audio.conversion.source_sample_rate != WHISPER_SAMPLE_RATE
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "mp3")]
+    fn test_parse_audio_track() {
+        let path = "src/test_data/test.mp3";
+        assert!(parse_audio_track(path, &TrackSelector::Index(0)).is_ok());
+        assert!(parse_audio_track(path, &TrackSelector::Index(1)).is_err());
+        assert!(parse_audio_track(path, &TrackSelector::Language("eng".into())).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mp3")]
+    fn test_probe_audio() -> Result<()> {
+        let info = probe_audio("src/test_data/test.mp3")?;
+        let audio = parse_audio_file("src/test_data/test.mp3")?;
+
+        assert_eq!(info.codec, "mp3");
+        assert_eq!(info.track_count, 1);
+        assert_eq!(info.sample_rate, Some(audio.conversion.source_sample_rate));
+        assert_eq!(info.channels, Some(audio.conversion.source_channels));
+
+        if let Some(duration_ms) = info.duration_ms {
+            let decoded_ms = audio.samples.len() as u64 * 1000 / WHISPER_SAMPLE_RATE as u64;
+            assert!(duration_ms.abs_diff(decoded_ms) < 100);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "mp3")]
+    fn test_audio_stream() -> Result<()> {
+        let path = "src/test_data/test.mp3";
+        let audio = parse_audio_file(path)?;
+
+        let chunk_size = 4000;
+        let chunks = AudioStream::open(path, &TrackSelector::Auto, chunk_size)?
+            .collect::<Result<Vec<_>>>()?;
+
+        let (last, rest) = chunks.split_last().unwrap();
+        assert!(rest.iter().all(|chunk| chunk.len() == chunk_size));
+        assert!(!last.is_empty() && last.len() <= chunk_size);
+        assert_eq!(chunks.concat(), audio.samples);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,417 @@
+use crate::manifest::Checksum;
+use crate::model_handler::PART_SUFFIX;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tar::{Archive, Builder, Entries, EntryType, Header};
+
+/// The first entry of a bundle, describing the models that follow it.
+pub const BUNDLE_MANIFEST: &str = "manifest.json";
+
+const BUNDLE_VERSION: u32 = 1;
+
+// The models are stored as "models/<name>" after the manifest
+const MODELS_PREFIX: &str = "models/";
+
+/// The manifest of a bundle: where its models go and their checksums.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub version: u32,
+    /// The model directory relative to the cache directory, such as "v2t/model".
+    pub directory: String,
+    pub models: BTreeMap<String, Checksum>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedModel {
+    /// The name in the bundle.
+    pub name: String,
+    /// The name in the model directory, another one when a different model had the name.
+    pub installed_as: String,
+    pub checksum: Checksum,
+    /// The same model was already installed, so nothing was written.
+    pub existed: bool,
+}
+
+/// Packs the models into a tar archive, after a manifest with their checksums.
+pub fn export_bundle(
+    archive: &Path,
+    directory: &str,
+    models: &[PathBuf],
+) -> Result<BundleManifest> {
+    let mut manifest = BundleManifest {
+        version: BUNDLE_VERSION,
+        directory: directory.to_string(),
+        models: BTreeMap::new(),
+    };
+
+    let mut names = vec![];
+    for path in models {
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_string(),
+            None => bail!("invalid model: {}", path.display()),
+        };
+
+        let checksum = Checksum::of_file(path).with_context(|| format!("can't read {name}"))?;
+        if manifest.models.insert(name.clone(), checksum).is_some() {
+            bail!("{name} is exported twice");
+        }
+        names.push(name);
+    }
+
+    // Written aside and renamed, so that an interrupted export leaves no broken bundle
+    let mut tmp = archive.as_os_str().to_owned();
+    tmp.push(PART_SUFFIX);
+    let tmp = PathBuf::from(tmp);
+
+    let result = write_bundle(&tmp, &manifest, &names, models);
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+
+    fs::rename(&tmp, archive)?;
+    Ok(manifest)
+}
+
+fn write_bundle(
+    archive: &Path,
+    manifest: &BundleManifest,
+    names: &[String],
+    models: &[PathBuf],
+) -> Result<()> {
+    let mut builder = Builder::new(BufWriter::new(File::create(archive)?));
+
+    let text = serde_json::to_vec_pretty(manifest)?;
+    builder.append_data(
+        &mut file_header(text.len() as u64),
+        BUNDLE_MANIFEST,
+        text.as_slice(),
+    )?;
+
+    for (name, path) in names.iter().zip(models) {
+        let size = manifest.models[name].size;
+
+        // The file could have changed since it was hashed
+        let file = File::open(path)?;
+        if file.metadata()?.len() != size {
+            bail!("{name} changed while it was exported");
+        }
+
+        let path = format!("{MODELS_PREFIX}{name}");
+        builder.append_data(&mut file_header(size), path, file)?;
+    }
+
+    builder
+        .into_inner()?
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+    Ok(())
+}
+
+fn file_header(size: u64) -> Header {
+    let mtime = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::Regular);
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header
+}
+
+/// Reads the manifest at the start of a bundle.
+pub fn read_manifest(archive: &Path) -> Result<BundleManifest> {
+    let file = File::open(archive).with_context(|| format!("open {}", archive.display()))?;
+    let mut reader = Archive::new(BufReader::new(file));
+    read_bundle_manifest(&mut reader.entries()?)
+        .with_context(|| format!("{} is not a model bundle", archive.display()))
+}
+
+fn read_bundle_manifest<R: Read>(entries: &mut Entries<'_, R>) -> Result<BundleManifest> {
+    let mut entry = match entries.next() {
+        Some(entry) => entry?,
+        None => bail!("the bundle is empty"),
+    };
+    if entry.path()?.as_ref() != Path::new(BUNDLE_MANIFEST) || entry.size() > 1 << 20 {
+        bail!("the bundle does not start with {BUNDLE_MANIFEST}");
+    }
+
+    let mut text = vec![];
+    entry.read_to_end(&mut text)?;
+
+    let manifest: BundleManifest = serde_json::from_slice(&text)?;
+    if manifest.version > BUNDLE_VERSION {
+        bail!("unsupported bundle version {}", manifest.version);
+    }
+
+    if let Some(name) = manifest.models.keys().find(|name| !is_valid_name(name)) {
+        bail!("invalid model name {name:?}");
+    }
+    Ok(manifest)
+}
+
+/// Verifies the models of a bundle against its manifest and installs them into `dir`.
+/// `check` is called on every model once it is extracted, to refuse the ones that are not
+/// models. Nothing is installed unless every model passes. A model that is already there is
+/// kept, one with the name of a different model is installed under a new name.
+pub fn import_bundle(
+    archive: &Path,
+    dir: &Path,
+    check: impl Fn(&str, &Path) -> Result<()>,
+) -> Result<(BundleManifest, Vec<ImportedModel>)> {
+    let file = File::open(archive).with_context(|| format!("open {}", archive.display()))?;
+    let mut reader = Archive::new(BufReader::new(file));
+    let mut entries = reader.entries()?;
+    let manifest = read_bundle_manifest(&mut entries)
+        .with_context(|| format!("{} is not a model bundle", archive.display()))?;
+
+    fs::create_dir_all(dir)?;
+
+    let mut extracted = vec![];
+    let result = extract_models(&mut entries, &manifest, dir, &mut extracted, check);
+    if let Err(e) = result {
+        for (_, tmp) in extracted.iter() {
+            let _ = fs::remove_file(tmp);
+        }
+        return Err(e);
+    }
+
+    let mut imported = vec![];
+    for (name, tmp) in extracted {
+        let checksum = manifest.models[&name].clone();
+        let (installed_as, existed) = install_name(dir, &name, &checksum)?;
+        if existed {
+            fs::remove_file(&tmp)?;
+        } else {
+            fs::rename(&tmp, dir.join(&installed_as))?;
+        }
+
+        imported.push(ImportedModel {
+            name,
+            installed_as,
+            checksum,
+            existed,
+        });
+    }
+
+    Ok((manifest, imported))
+}
+
+// Writes every model next to its final place and checks it against the manifest
+fn extract_models<R: Read>(
+    entries: &mut Entries<'_, R>,
+    manifest: &BundleManifest,
+    dir: &Path,
+    extracted: &mut Vec<(String, PathBuf)>,
+    check: impl Fn(&str, &Path) -> Result<()>,
+) -> Result<()> {
+    for entry in entries {
+        let mut entry = entry.context("the bundle is truncated")?;
+        let path = entry.path()?.to_string_lossy().to_string();
+        if !entry.header().entry_type().is_file() {
+            bail!("{path} is not a regular file");
+        }
+
+        let name = match path.strip_prefix(MODELS_PREFIX) {
+            Some(name) if manifest.models.contains_key(name) => name.to_string(),
+            _ => bail!("{path} is not in the bundle manifest"),
+        };
+
+        if extracted.iter().any(|(other, _)| *other == name) {
+            bail!("{name} is twice in the bundle");
+        }
+
+        let tmp = dir.join(format!("{name}.bundle{PART_SUFFIX}"));
+        extracted.push((name.clone(), tmp.clone()));
+
+        let mut writer = HashWriter {
+            inner: BufWriter::new(File::create(&tmp)?),
+            hasher: Sha256::new(),
+        };
+        let size = entry.size();
+        let copied = io::copy(&mut entry, &mut writer)
+            .with_context(|| format!("the bundle is truncated in {name}"))?;
+        if copied != size {
+            bail!("the bundle is truncated in {name}");
+        }
+
+        writer.inner.flush()?;
+        let sha256 = format!("{:x}", writer.hasher.finalize());
+        let expected = &manifest.models[&name];
+        if size != expected.size || !sha256.eq_ignore_ascii_case(&expected.sha256) {
+            bail!("{name} does not match its checksum in the bundle");
+        }
+
+        check(&name, &tmp).with_context(|| format!("can't import {name}"))?;
+    }
+
+    if let Some(name) = manifest
+        .models
+        .keys()
+        .find(|name| !extracted.iter().any(|(other, _)| other == *name))
+    {
+        bail!("{name} is missing from the bundle");
+    }
+    Ok(())
+}
+
+// The name to install a model under, and whether the same model already has it
+fn install_name(dir: &Path, name: &str, checksum: &Checksum) -> Result<(String, bool)> {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{extension}")),
+        _ => (name, String::new()),
+    };
+
+    for n in 0.. {
+        let candidate = match n {
+            0 => name.to_string(),
+            n => format!("{stem}-{n}{extension}"),
+        };
+
+        let path = dir.join(&candidate);
+        if !path.exists() {
+            return Ok((candidate, false));
+        }
+
+        if path.is_file() && Checksum::of_file(&path)? == *checksum {
+            return Ok((candidate, true));
+        }
+    }
+    unreachable!()
+}
+
+// A plain file name, that can't escape the model directory
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', '\0'])
+        && !name.ends_with(PART_SUFFIX)
+}
+
+struct HashWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK_SIZE: usize = 512;
+
+    fn test_dir(name: &str) -> Result<PathBuf> {
+        let dir = std::env::temp_dir().join(format!("transcribe-test-bundle-{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src"))?;
+        fs::write(dir.join("src/ggml-a.bin"), "abc")?;
+        fs::write(dir.join("src/ggml-b.bin"), vec![7; 1000])?;
+        Ok(dir)
+    }
+
+    #[test]
+    fn test_bundle() -> Result<()> {
+        let dir = test_dir("round-trip")?;
+        let archive = dir.join("models.tar");
+        let models = [dir.join("src/ggml-a.bin"), dir.join("src/ggml-b.bin")];
+        let manifest = export_bundle(&archive, "v2t/model", &models)?;
+        assert_eq!(manifest.models.len(), 2);
+        assert_eq!(fs::metadata(&archive)?.len() % BLOCK_SIZE as u64, 0);
+        assert_eq!(read_manifest(&archive)?, manifest);
+
+        let dest = dir.join("dest");
+        let (_, imported) = import_bundle(&archive, &dest, |_, _| Ok(()))?;
+        assert_eq!(fs::read(dest.join("ggml-b.bin"))?, vec![7; 1000]);
+        assert!(imported
+            .iter()
+            .all(|m| m.name == m.installed_as && !m.existed));
+
+        // Installed again, a and b are already there and another a takes a new name
+        fs::write(dest.join("ggml-a.bin"), "xyz")?;
+        let (_, imported) = import_bundle(&archive, &dest, |_, _| Ok(()))?;
+        assert_eq!(imported[0].installed_as, "ggml-a-1.bin");
+        assert_eq!(fs::read(dest.join("ggml-a-1.bin"))?, b"abc");
+        assert!(imported[1].existed);
+
+        // The renamed a is found again under its new name
+        let (_, imported) = import_bundle(&archive, &dest, |_, _| Ok(()))?;
+        assert_eq!(imported[0].installed_as, "ggml-a-1.bin");
+        assert!(imported[0].existed);
+
+        let names = fs::read_dir(&dest)?.count();
+        assert_eq!(names, 3);
+
+        // Refused by `check`, nothing is installed
+        let dest = dir.join("refused");
+        let e = import_bundle(&archive, &dest, |name, _| match name {
+            "ggml-b.bin" => bail!("not a model"),
+            _ => Ok(()),
+        })
+        .unwrap_err();
+        assert!(format!("{e:?}").contains("not a model"), "{e:?}");
+        assert_eq!(fs::read_dir(&dest)?.count(), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_corrupted() -> Result<()> {
+        let dir = test_dir("corrupted")?;
+        let archive = dir.join("models.tar");
+        let models = [dir.join("src/ggml-a.bin"), dir.join("src/ggml-b.bin")];
+        export_bundle(&archive, "v2t/model", &models)?;
+
+        // A byte of ggml-b.bin, the last entry before the two empty blocks
+        let mut bytes = fs::read(&archive)?;
+        let offset = bytes.len() - BLOCK_SIZE * 2 - 1024 + 10;
+        bytes[offset] ^= 0xff;
+        fs::write(&archive, &bytes)?;
+
+        let dest = dir.join("dest");
+        let e = import_bundle(&archive, &dest, |_, _| Ok(())).unwrap_err();
+        assert!(e.to_string().contains("ggml-b.bin"), "{e}");
+        assert_eq!(fs::read_dir(&dest)?.count(), 0);
+
+        // A truncated bundle
+        fs::write(&archive, &bytes[..bytes.len() / 2])?;
+        assert!(import_bundle(&archive, &dest, |_, _| Ok(())).is_err());
+        assert_eq!(fs::read_dir(&dest)?.count(), 0);
+
+        fs::write(&archive, "not a tar")?;
+        assert!(read_manifest(&archive).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_valid_name() {
+        assert!(is_valid_name("ggml-base.bin"));
+        assert!(!is_valid_name("../ggml-base.bin"));
+        assert!(!is_valid_name(".."));
+        assert!(!is_valid_name("a\\b"));
+        assert!(!is_valid_name("ggml-base.bin.part"));
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::manifest::Checksum;
+
+const BUNDLED: &str = include_str!("../catalog.json");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelKind {
+    #[default]
+    VoiceToText,
+    TextToVoice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Languages {
+    Multilingual,
+    /// The `.en` models, which only transcribe English.
+    English,
+}
+
+impl Languages {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Languages::Multilingual => "multilingual",
+            Languages::English => "english",
+        }
+    }
+}
+
+/// A downloadable model.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    /// File name of the model once downloaded.
+    pub name: String,
+    /// Such as base, large-v3 or distil-large-v3.
+    pub family: String,
+    #[serde(default, skip_serializing_if = "is_voice_to_text")]
+    pub kind: ModelKind,
+    pub languages: Languages,
+    /// Such as q5_0, q5_1 or q8_0, `None` for the full precision model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantization: Option<String>,
+    /// Approximate size, for display. The checksum has the exact one.
+    pub size_mb: u64,
+    /// When it is missing, the download is only checked against the checksum the server
+    /// announces, and the model is recorded as unverified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<Checksum>,
+    pub url: String,
+}
+
+fn is_voice_to_text(kind: &ModelKind) -> bool {
+    *kind == ModelKind::VoiceToText
+}
+
+/// The bundled list of models, with the entries of the user's catalog file replacing
+/// the bundled ones of the same name or added after them.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    entries: Vec<CatalogEntry>,
+}
+
+impl Catalog {
+    pub fn bundled() -> Catalog {
+        Catalog {
+            entries: serde_json::from_str(BUNDLED).expect("the bundled catalog is valid"),
+        }
+    }
+
+    /// The bundled catalog, merged with the JSON array of entries at `path` if it exists.
+    pub fn load(path: &Path) -> Result<Catalog> {
+        let mut catalog = Catalog::bundled();
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                let entries = serde_json::from_str(&text)
+                    .with_context(|| format!("invalid catalog {}", path.display()))?;
+                catalog.merge(entries);
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e.into()),
+        }
+        Ok(catalog)
+    }
+
+    pub fn merge(&mut self, entries: Vec<CatalogEntry>) {
+        for entry in entries {
+            match self.entries.iter_mut().find(|e| e.name == entry.name) {
+                Some(e) => *e = entry,
+                None => self.entries.push(entry),
+            }
+        }
+    }
+
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, kind: ModelKind, name: &str) -> Option<&CatalogEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.kind == kind && e.name == name)
+    }
+
+    pub fn of_kind(&self, kind: ModelKind) -> impl Iterator<Item = &CatalogEntry> {
+        self.entries.iter().filter(move |e| e.kind == kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog() -> Result<()> {
+        let mut catalog = Catalog::bundled();
+        for name in [
+            "ggml-base.en.bin",
+            "ggml-small-q5_1.bin",
+            "ggml-medium-q8_0.bin",
+            "ggml-large-v1.bin",
+            "ggml-large-v2.bin",
+            "ggml-large-v3.bin",
+            "ggml-large-v3-turbo.bin",
+            "ggml-distil-large-v3.bin",
+        ] {
+            assert!(
+                catalog.get(ModelKind::VoiceToText, name).is_some(),
+                "{name}"
+            );
+        }
+        assert_eq!(
+            catalog
+                .get(ModelKind::VoiceToText, "ggml-tiny.en-q8_0.bin")
+                .map(|e| (e.languages, e.quantization.as_deref())),
+            Some((Languages::English, Some("q8_0")))
+        );
+        assert!(catalog
+            .get(ModelKind::TextToVoice, "ggml-base.bin")
+            .is_none());
+
+        let count = catalog.entries().len();
+        let entries: Vec<CatalogEntry> = serde_json::from_str(
+            r#"[
+                {"name": "ggml-base.bin", "family": "base", "languages": "multilingual",
+                 "size_mb": 142, "url": "http://mirror/ggml-base.bin",
+                 "checksum": {"size": 147951465, "sha256": "abc"}},
+                {"name": "custom.bin", "family": "custom", "languages": "english",
+                 "size_mb": 1, "url": "http://mirror/custom.bin"}
+            ]"#,
+        )?;
+        catalog.merge(entries);
+
+        assert_eq!(catalog.entries().len(), count + 1);
+        let base = catalog
+            .get(ModelKind::VoiceToText, "ggml-base.bin")
+            .unwrap();
+        assert_eq!(base.url, "http://mirror/ggml-base.bin");
+        assert_eq!(base.checksum.as_ref().map(|c| c.size), Some(147951465));
+        assert!(catalog.get(ModelKind::VoiceToText, "custom.bin").is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundled_checksums() {
+        // Pinned by `cargo run --example catalog_checksums`
+        for entry in Catalog::bundled().entries() {
+            if let Some(checksum) = &entry.checksum {
+                assert_eq!(checksum.sha256.len(), 64, "{}", entry.name);
+                assert!(
+                    checksum.sha256.bytes().all(|b| b.is_ascii_hexdigit()),
+                    "{}",
+                    entry.name
+                );
+                assert_eq!(
+                    entry.size_mb,
+                    (checksum.size + (1 << 19)) >> 20,
+                    "{}",
+                    entry.name
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::transcriber::{Segment, TranscriberOutput};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Ass,
+    Ssa,
+    Lrc,
+    Tsv,
+    /// Segments, tokens and audio conversion as serialized by serde, `from_json` reads it back.
+    Json,
+}
+
+impl SubtitleFormat {
+    pub fn all() -> [SubtitleFormat; 7] {
+        [
+            SubtitleFormat::Srt,
+            SubtitleFormat::Vtt,
+            SubtitleFormat::Ass,
+            SubtitleFormat::Ssa,
+            SubtitleFormat::Lrc,
+            SubtitleFormat::Tsv,
+            SubtitleFormat::Json,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "SubRip",
+            SubtitleFormat::Vtt => "WebVTT",
+            SubtitleFormat::Ass => "Advanced SubStation Alpha",
+            SubtitleFormat::Ssa => "SubStation Alpha",
+            SubtitleFormat::Lrc => "LRC",
+            SubtitleFormat::Tsv => "TSV",
+            SubtitleFormat::Json => "JSON",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+            SubtitleFormat::Ass => "ass",
+            SubtitleFormat::Ssa => "ssa",
+            SubtitleFormat::Lrc => "lrc",
+            SubtitleFormat::Tsv => "tsv",
+            SubtitleFormat::Json => "json",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<SubtitleFormat> {
+        let extension = extension.to_lowercase();
+        Self::all().into_iter().find(|f| f.extension() == extension)
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Option<SubtitleFormat> {
+        path.as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(Self::from_extension)
+    }
+}
+
+pub fn render(output: &TranscriberOutput, format: SubtitleFormat) -> Result<String> {
+    let segments = output.get_segments();
+
+    Ok(match format {
+        SubtitleFormat::Srt => to_srt(segments),
+        SubtitleFormat::Vtt => to_vtt(segments),
+        SubtitleFormat::Ass => to_ass(segments),
+        SubtitleFormat::Ssa => to_ssa(segments),
+        SubtitleFormat::Lrc => to_lrc(segments),
+        SubtitleFormat::Tsv => to_tsv(segments),
+        SubtitleFormat::Json => to_json(output)?,
+    })
+}
+
+/// Writes `output` to `path` in `format`.
+pub fn save(
+    output: &TranscriberOutput,
+    format: SubtitleFormat,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let content = render(output, format)?;
+    std::fs::write(path, content).with_context(|| format!("failed to write {}", path.display()))
+}
+
+pub fn to_srt(segments: &[Segment]) -> String {
+    let mut content = String::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        content.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            clock_time(segment.start_timestamp, ','),
+            clock_time(segment.end_timestamp, ','),
+            segment.text.trim()
+        ));
+    }
+
+    content
+}
+
+pub fn to_vtt(segments: &[Segment]) -> String {
+    let mut content = String::from("WEBVTT\n\n");
+
+    for segment in segments.iter() {
+        let text = segment
+            .text
+            .trim()
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+
+        content.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            clock_time(segment.start_timestamp, '.'),
+            clock_time(segment.end_timestamp, '.'),
+            text
+        ));
+    }
+
+    content
+}
+
+pub fn to_ass(segments: &[Segment]) -> String {
+    let mut content = String::from(
+        "[Script Info]
+ScriptType: v4.00+
+PlayResX: 384
+PlayResY: 288
+ScaledBorderAndShadow: yes
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,16,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,1,0,2,10,10,10,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+",
+    );
+
+    for segment in segments.iter() {
+        content.push_str(&format!(
+            "Dialogue: 0,{},{},Default,,0,0,0,,{}\n",
+            ass_time(segment.start_timestamp),
+            ass_time(segment.end_timestamp),
+            ass_text(&segment.text)
+        ));
+    }
+
+    content
+}
+
+pub fn to_ssa(segments: &[Segment]) -> String {
+    let mut content = String::from(
+        "[Script Info]
+ScriptType: v4.00
+PlayResX: 384
+PlayResY: 288
+
+[V4 Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, TertiaryColour, BackColour, Bold, Italic, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, AlphaLevel, Encoding
+Style: Default,Arial,16,16777215,255,0,0,0,0,1,1,0,2,10,10,10,0,1
+
+[Events]
+Format: Marked, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+",
+    );
+
+    for segment in segments.iter() {
+        content.push_str(&format!(
+            "Dialogue: Marked=0,{},{},Default,,0,0,0,,{}\n",
+            ass_time(segment.start_timestamp),
+            ass_time(segment.end_timestamp),
+            ass_text(&segment.text)
+        ));
+    }
+
+    content
+}
+
+pub fn to_lrc(segments: &[Segment]) -> String {
+    let mut content = String::new();
+
+    for segment in segments.iter() {
+        let ms = segment.start_timestamp.max(0);
+        content.push_str(&format!(
+            "[{:02}:{:02}.{:02}]{}\n",
+            ms / 60_000,
+            ms / 1000 % 60,
+            ms / 10 % 100,
+            single_line(&segment.text)
+        ));
+    }
+
+    content
+}
+
+/// Same layout as whisper.cpp: timestamps in milliseconds, one segment per line.
+pub fn to_tsv(segments: &[Segment]) -> String {
+    let mut content = String::from("start\tend\ttext\n");
+
+    for segment in segments.iter() {
+        content.push_str(&format!(
+            "{}\t{}\t{}\n",
+            segment.start_timestamp,
+            segment.end_timestamp,
+            single_line(&segment.text).replace('\t', " ")
+        ));
+    }
+
+    content
+}
+
+pub fn to_json(output: &TranscriberOutput) -> Result<String> {
+    serde_json::to_string_pretty(output).context("failed to serialize transcription")
+}
+
+pub fn from_json(content: &str) -> Result<TranscriberOutput> {
+    serde_json::from_str(content).context("failed to parse transcription")
+}
+
+/// `HH:MM:SS<separator>mmm`, the time format of SRT and WebVTT.
+fn clock_time(ms: i64, separator: char) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{:02}:{:02}:{:02}{separator}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// `H:MM:SS.cc`, ASS and SSA only have centiseconds.
+fn ass_time(ms: i64) -> String {
+    let ms = ms.max(0);
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms / 10 % 100
+    )
+}
+
+fn ass_text(text: &str) -> String {
+    text.trim().replace("\r\n", "\n").replace('\n', "\\N")
+}
+
+fn single_line(text: &str) -> String {
+    text.trim().lines().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments() -> Vec<Segment> {
+        vec![
+            Segment {
+                index: 0,
+                start_timestamp: 0,
+                end_timestamp: 2500,
+                text: " Hello <world>".to_string(),
+                token_range: 0..3,
+            },
+            Segment {
+                index: 1,
+                start_timestamp: 3_723_040,
+                end_timestamp: 3_725_000,
+                text: " Second\nline".to_string(),
+                token_range: 3..5,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_srt() {
+        assert_eq!(
+            to_srt(&segments()),
+            "1\n00:00:00,000 --> 00:00:02,500\nHello <world>\n\n2\n01:02:03,040 --> 01:02:05,000\nSecond\nline\n\n"
+        );
+    }
+
+    #[test]
+    fn test_vtt() {
+        let vtt = to_vtt(&segments());
+        assert!(vtt.starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:02.500\nHello &lt;world&gt;\n"));
+        assert!(vtt.contains("01:02:03.040 --> 01:02:05.000\n"));
+    }
+
+    #[test]
+    fn test_ass() {
+        let ass = to_ass(&segments());
+        assert!(ass.contains("Dialogue: 0,0:00:00.00,0:00:02.50,Default,,0,0,0,,Hello <world>\n"));
+        assert!(ass.contains("Dialogue: 0,1:02:03.04,1:02:05.00,Default,,0,0,0,,Second\\Nline\n"));
+
+        let ssa = to_ssa(&segments());
+        assert!(ssa.contains("[V4 Styles]"));
+        assert!(ssa.contains("Dialogue: Marked=0,0:00:00.00,0:00:02.50,"));
+    }
+
+    #[test]
+    fn test_lrc_and_tsv() {
+        assert_eq!(
+            to_lrc(&segments()),
+            "[00:00.00]Hello <world>\n[62:03.04]Second line\n"
+        );
+        assert_eq!(
+            to_tsv(&segments()),
+            "start\tend\ttext\n0\t2500\tHello <world>\n3723040\t3725000\tSecond line\n"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let output = TranscriberOutput::new(segments(), vec![]);
+        let restored = from_json(&to_json(&output).unwrap()).unwrap();
+
+        assert_eq!(restored.get_segments(), output.get_segments());
+        assert_eq!(restored.get_text(), output.get_text());
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            SubtitleFormat::from_path("/tmp/a.SRT"),
+            Some(SubtitleFormat::Srt)
+        );
+        assert_eq!(
+            SubtitleFormat::from_path("a.ssa"),
+            Some(SubtitleFormat::Ssa)
+        );
+        assert_eq!(SubtitleFormat::from_path("a.txt"), None);
+        assert_eq!(SubtitleFormat::from_path("a"), None);
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::transcriber::Segment;
+
+/// Stops a running transcription from another thread. Whisper checks it between its compute
+/// steps, so a job stops within a second or two even on large models.
+#[derive(Debug, Clone, Default)]
+pub struct AbortHandle(Arc<AtomicBool>);
+
+impl AbortHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn abort(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Returned as error when a transcription was aborted, check it with `e.is::<Aborted>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Aborted;
+
+impl fmt::Display for Aborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transcription aborted")
+    }
+}
+
+impl std::error::Error for Aborted {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// From 0 to 100, `None` when the length of the audio is unknown.
+    pub percent: Option<f32>,
+    /// Set when a segment was just transcribed.
+    pub segment: Option<Segment>,
+}
+
+pub type ProgressCallback = Arc<Mutex<dyn FnMut(Progress) + Send>>;
+
+// How often an abort is checked while waiting for a slot
+const SLOT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Progress callback, abort handle and inference slots of one transcription.
+#[derive(Clone, Default)]
+pub struct JobControl {
+    abort: AbortHandle,
+    on_progress: Option<ProgressCallback>,
+    slots: Option<Arc<Semaphore>>,
+}
+
+impl JobControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_abort_handle(mut self, handle: AbortHandle) -> Self {
+        self.abort = handle;
+        self
+    }
+
+    /// `callback` is called from the transcribing thread.
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(Progress) + Send + 'static,
+    {
+        self.on_progress = Some(Arc::new(Mutex::new(callback)));
+        self
+    }
+
+    /// The job waits for a permit of `slots` before it starts, so that the jobs sharing
+    /// them run at most that many inferences at once.
+    pub fn with_slots(mut self, slots: Arc<Semaphore>) -> Self {
+        self.slots = Some(slots);
+        self
+    }
+
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.abort.clone()
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.abort.is_aborted()
+    }
+
+    /// Waits for a free slot, if any were given, and gives up once the job is aborted.
+    pub(crate) async fn acquire_slot(&self) -> anyhow::Result<Option<OwnedSemaphorePermit>> {
+        let slots = match &self.slots {
+            Some(slots) => slots,
+            None => return Ok(None),
+        };
+
+        loop {
+            if self.is_aborted() {
+                return Err(Aborted.into());
+            }
+
+            let acquire = slots.clone().acquire_owned();
+            if let Ok(permit) = tokio::time::timeout(SLOT_POLL_INTERVAL, acquire).await {
+                return Ok(Some(permit?));
+            }
+        }
+    }
+
+    pub(crate) fn has_progress(&self) -> bool {
+        self.on_progress.is_some()
+    }
+
+    pub(crate) fn report(&self, progress: Progress) {
+        if let Some(on_progress) = &self.on_progress {
+            if let Ok(mut on_progress) = on_progress.lock() {
+                on_progress(progress);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for JobControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobControl")
+            .field("abort", &self.abort)
+            .field("on_progress", &self.on_progress.is_some())
+            .field("slots", &self.slots)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_control() {
+        let handle = AbortHandle::new();
+        let reports = Arc::new(Mutex::new(vec![]));
+
+        let control = JobControl::new()
+            .with_abort_handle(handle.clone())
+            .on_progress({
+                let reports = reports.clone();
+                move |p| reports.lock().unwrap().push(p.percent)
+            });
+
+        control.report(Progress {
+            percent: Some(50.0),
+            segment: None,
+        });
+        assert_eq!(*reports.lock().unwrap(), vec![Some(50.0)]);
+
+        assert!(!control.is_aborted());
+        handle.abort();
+        assert!(control.clone().is_aborted());
+
+        let e = anyhow::Error::new(Aborted);
+        assert!(e.is::<Aborted>());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_slot() -> anyhow::Result<()> {
+        assert!(JobControl::new().acquire_slot().await?.is_none());
+
+        let slots = Arc::new(Semaphore::new(1));
+        let first = JobControl::new().with_slots(slots.clone());
+        let permit = first.acquire_slot().await?;
+        assert!(permit.is_some());
+
+        // Aborted while it waits for the slot the first job holds
+        let handle = AbortHandle::new();
+        let second = JobControl::new()
+            .with_abort_handle(handle.clone())
+            .with_slots(slots.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            handle.abort();
+        });
+        let e = second.acquire_slot().await.unwrap_err();
+        assert!(e.is::<Aborted>());
+
+        drop(permit);
+        assert!(JobControl::new()
+            .with_slots(slots)
+            .acquire_slot()
+            .await?
+            .is_some());
+        Ok(())
+    }
+}
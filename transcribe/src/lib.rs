@@ -1,3 +1,16 @@
-mod audio_parser;
+pub mod audio_parser;
+pub mod bundle;
+pub mod catalog;
+pub mod export;
+pub mod job;
+pub mod manifest;
 pub mod model_handler;
+pub mod model_header;
+pub mod options;
 pub mod transcriber;
+pub mod vad;
+
+pub use audio_parser::{
+    parse_audio_file, parse_audio_track, probe_audio, supported_extensions, AudioConversion,
+    AudioInfo, DecodedAudio, TrackSelector,
+};
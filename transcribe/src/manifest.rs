@@ -0,0 +1,205 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Expected size and SHA-256 of a model file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checksum {
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl Checksum {
+    /// Size and hash of the file as it is on disk.
+    pub fn of_file(path: &Path) -> Result<Checksum> {
+        Ok(Checksum {
+            size: fs::metadata(path)?.len(),
+            sha256: sha256_file(path)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verification {
+    Ok,
+    Mismatch,
+    /// The manifest has no checksum for the file.
+    Unknown,
+    /// The file matches a checksum that was not pinned in advance, only computed or
+    /// announced by the server it was downloaded from.
+    Unverified,
+}
+
+/// Checksums of the models of a directory by file name, kept as JSON next to it.
+/// Downloaded models are pinned to the checksum the server announced, imported ones
+/// to the hash of the file when it was imported. A model downloaded without a checksum
+/// pinned in advance is recorded as unverified.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(skip)]
+    path: PathBuf,
+    models: BTreeMap<String, Checksum>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    unverified: BTreeSet<String>,
+}
+
+impl Manifest {
+    /// An empty manifest when the file does not exist yet.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Manifest> {
+        let path = path.into();
+        let mut manifest = match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str::<Manifest>(&text)
+                .with_context(|| format!("invalid manifest {}", path.display()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Manifest::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        manifest.path = path;
+        Ok(manifest)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Written aside and renamed, so that a crash never leaves half a manifest
+        let mut tmp = self.path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Checksum> {
+        self.models.get(name)
+    }
+
+    /// The checksum of the model when it was pinned in advance, not just recorded after
+    /// an unverified download.
+    pub fn pinned(&self, name: &str) -> Option<&Checksum> {
+        match self.unverified.contains(name) {
+            true => None,
+            false => self.get(name),
+        }
+    }
+
+    pub fn insert(&mut self, name: &str, checksum: Checksum) {
+        self.models.insert(name.to_string(), checksum);
+        self.unverified.remove(name);
+    }
+
+    /// Records the checksum of a model that nothing vouches for, so that it can still be
+    /// checked for corruption later.
+    pub fn insert_unverified(&mut self, name: &str, checksum: Checksum) {
+        self.models.insert(name.to_string(), checksum);
+        self.unverified.insert(name.to_string());
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.models.remove(name);
+        self.unverified.remove(name);
+    }
+
+    /// Compares the file with its checksum. Only the size is checked when `quick` is set,
+    /// which does not read the file.
+    pub fn verify(&self, name: &str, path: &Path, quick: bool) -> Result<Verification> {
+        let expected = match self.get(name) {
+            Some(checksum) => checksum,
+            None => return Ok(Verification::Unknown),
+        };
+
+        if fs::metadata(path)?.len() != expected.size {
+            return Ok(Verification::Mismatch);
+        }
+
+        if !quick && !sha256_file(path)?.eq_ignore_ascii_case(&expected.sha256) {
+            Ok(Verification::Mismatch)
+        } else if self.unverified.contains(name) {
+            Ok(Verification::Unverified)
+        } else {
+            Ok(Verification::Ok)
+        }
+    }
+}
+
+/// Hex SHA-256 of the file, read in chunks so that large models are fine.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest() -> Result<()> {
+        let dir = std::env::temp_dir().join("transcribe-test-manifest");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+
+        let model = dir.join("ggml-test.bin");
+        fs::write(&model, "abc")?;
+
+        let mut manifest = Manifest::load(dir.join("manifest.json"))?;
+        assert_eq!(
+            manifest.verify("ggml-test.bin", &model, false)?,
+            Verification::Unknown
+        );
+
+        let checksum = Checksum::of_file(&model)?;
+        assert_eq!(
+            checksum.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        manifest.insert("ggml-test.bin", checksum.clone());
+        manifest.save()?;
+
+        let manifest = Manifest::load(dir.join("manifest.json"))?;
+        assert_eq!(manifest.get("ggml-test.bin"), Some(&checksum));
+        assert_eq!(
+            manifest.verify("ggml-test.bin", &model, false)?,
+            Verification::Ok
+        );
+
+        // Same size, other content
+        fs::write(&model, "abd")?;
+        assert_eq!(
+            manifest.verify("ggml-test.bin", &model, true)?,
+            Verification::Ok
+        );
+        assert_eq!(
+            manifest.verify("ggml-test.bin", &model, false)?,
+            Verification::Mismatch
+        );
+
+        fs::write(&model, "ab")?;
+        assert_eq!(
+            manifest.verify("ggml-test.bin", &model, true)?,
+            Verification::Mismatch
+        );
+
+        // Recorded after a download nothing vouched for
+        let mut manifest = Manifest::load(dir.join("manifest.json"))?;
+        fs::write(&model, "abc")?;
+        manifest.insert_unverified("ggml-test.bin", checksum.clone());
+        manifest.save()?;
+
+        let manifest = Manifest::load(dir.join("manifest.json"))?;
+        assert_eq!(manifest.pinned("ggml-test.bin"), None);
+        assert_eq!(
+            manifest.verify("ggml-test.bin", &model, false)?,
+            Verification::Unverified
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
@@ -1,5 +1,13 @@
-use anyhow::Result;
-use reqwest::{Client, Proxy};
+use crate::manifest::Checksum;
+use anyhow::{anyhow, bail, Result};
+use reqwest::header::{CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use reqwest::redirect::Policy;
+use reqwest::{Client, NoProxy, Proxy, Response, StatusCode};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
 
 pub struct ModelHandler {
     model_name: String, // list of downloaded models
@@ -28,11 +36,11 @@ impl ModelHandler {
         Ok(())
     }
 
-    pub async fn download_model(&self, proxy_info: Option<(&str, u16)>) -> Result<()> {
+    pub async fn download_model(&self, options: &DownloadOptions) -> Result<()> {
         if !self.is_model_existing() {
             self.setup_directory()?;
         }
-        download_model(&self.models_dir, &self.model_name, proxy_info).await
+        download_model(&self.models_dir, &self.model_name, options).await
     }
 
     pub fn is_model_existing(&self) -> bool {
@@ -44,27 +52,380 @@ impl ModelHandler {
     }
 }
 
+/// Suffix of a download that is still running or was interrupted. It is resumed from
+/// where it stopped by the next download of the same model.
+pub const PART_SUFFIX: &str = ".part";
+
+/// Bytes of a model downloaded so far. `total` is `None` when the server does not tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+impl DownloadProgress {
+    /// From 0 to 1, `None` when the size is unknown.
+    pub fn fraction(&self) -> Option<f32> {
+        match self.total {
+            Some(total) if total > 0 => Some(self.downloaded as f32 / total as f32),
+            _ => None,
+        }
+    }
+}
+
+pub fn is_partial_download(name: &str) -> bool {
+    name.ends_with(PART_SUFFIX)
+}
+
+pub const HUGGINGFACE_URL: &str = "https://huggingface.co";
+
+/// How models are fetched.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    /// Base URLs tried in order in place of `HUGGINGFACE_URL`. The URLs are used as they
+    /// are when it is empty.
+    pub mirrors: Vec<String>,
+    pub proxy: Option<ProxyOptions>,
+    pub connect_timeout: Option<Duration>,
+    /// Longest wait for the response, and then for each chunk of it.
+    pub read_timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProxyOptions {
+    /// Such as `http://host:port`, `https://`, `socks5://` or `socks5h://`, which
+    /// resolves the host names through the proxy.
+    pub url: String,
+    /// No authentication when it is empty.
+    pub username: String,
+    pub password: String,
+    /// Comma separated hosts, domains and IP ranges that are connected to directly.
+    pub no_proxy: String,
+}
+
+impl DownloadOptions {
+    /// The URLs to try for a file, one per mirror for a Hugging Face file.
+    pub fn urls(&self, url: &str) -> Vec<String> {
+        match url.strip_prefix(HUGGINGFACE_URL) {
+            Some(path) if !self.mirrors.is_empty() => self
+                .mirrors
+                .iter()
+                .map(|mirror| format!("{}{path}", mirror.trim_end_matches('/')))
+                .collect(),
+            _ => vec![url.to_string()],
+        }
+    }
+
+    fn client(&self, redirect: Policy) -> Result<Client> {
+        let mut builder = Client::builder().redirect(redirect);
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+
+        if let Some(options) = &self.proxy {
+            let mut proxy =
+                Proxy::all(&options.url)?.no_proxy(NoProxy::from_string(&options.no_proxy));
+            if !options.username.is_empty() {
+                proxy = proxy.basic_auth(&options.username, &options.password);
+            }
+            builder = builder.proxy(proxy);
+        }
+        Ok(builder.build()?)
+    }
+}
+
 pub async fn download_model(
     models_dir: &str,
     model_name: &str,
-    proxy_info: Option<(&str, u16)>,
+    options: &DownloadOptions,
 ) -> Result<()> {
-    let base_url = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
-    let url = format!("{}/{}", base_url, model_name);
+    let url = format!(
+        "{HUGGINGFACE_URL}/ggerganov/whisper.cpp/resolve/main/{}",
+        model_name
+    );
+
+    download_model_with_progress(models_dir, model_name, &url, options, None, |_| ()).await?;
+    Ok(())
+}
+
+/// Like `download_model`, calling `on_progress` after each received chunk. The mirrors
+/// are tried in order, each one resuming what the previous ones downloaded. The file is
+/// checked against `expected`, or else against the checksum the server announces, and
+/// the checksum of the installed model is returned. The announced checksum comes from
+/// the same server as the file, so it only tells a broken transfer.
+pub async fn download_model_with_progress(
+    models_dir: &str,
+    model_name: &str,
+    url: &str,
+    options: &DownloadOptions,
+    expected: Option<Checksum>,
+    mut on_progress: impl FnMut(DownloadProgress),
+) -> Result<Checksum> {
+    let path = Path::new(models_dir).join(model_name);
+    let _lock = DownloadLock::acquire(&path)?;
+    let (head_client, client) = (
+        options.client(Policy::none())?,
+        options.client(Policy::default())?,
+    );
+
+    let mut errors = vec![];
+    let mut expected = expected;
+    for url in options.urls(url) {
+        if expected.is_none() {
+            expected = announced_checksum(&head_client, &url).await;
+        }
+
+        match download_file(
+            &client,
+            &url,
+            &path,
+            expected.as_ref(),
+            options.read_timeout,
+            &mut on_progress,
+        )
+        .await
+        {
+            Ok(checksum) => return Ok(checksum),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    match errors.len() {
+        1 => Err(errors.remove(0)),
+        _ => {
+            let errors: Vec<String> = errors.iter().map(|e| format!("{e:#}")).collect();
+            Err(anyhow!(errors.join("\n")))
+        }
+    }
+}
 
-    let client = if let Some((ip, port)) = proxy_info {
-        let proxy = Proxy::all(format!("socks5://{}:{}", ip, port))?;
-        Client::builder().proxy(proxy).build()?
+/// Hugging Face announces the SHA-256 and the size of its LFS files in the headers of
+/// the redirect to its CDN. `None` when they are missing or can not be fetched.
+pub async fn announced_checksum(client: &Client, url: &str) -> Option<Checksum> {
+    let response = client.head(url).send().await.ok()?;
+    let headers = response.headers();
+
+    let sha256 = headers
+        .get("x-linked-etag")?
+        .to_str()
+        .ok()?
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .to_lowercase();
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let size = headers.get("x-linked-size")?.to_str().ok()?.parse().ok()?;
+    Some(Checksum { size, sha256 })
+}
+
+/// Streams `url` into `path` with the `.part` suffix, resuming a previous partial
+/// download with a Range request, and renames it to `path` once it is complete. An
+/// interrupted download never leaves a broken file at `path`, and neither does a file
+/// that does not match `expected`. The download fails when the server sends nothing
+/// for `read_timeout`.
+pub async fn download_file(
+    client: &Client,
+    url: &str,
+    path: &Path,
+    expected: Option<&Checksum>,
+    read_timeout: Option<Duration>,
+    mut on_progress: impl FnMut(DownloadProgress),
+) -> Result<Checksum> {
+    let part_path = part_path(path);
+    let mut downloaded = fs::metadata(&part_path).await.map_or(0, |m| m.len());
+
+    let mut response = with_timeout(url, read_timeout, send(client, url, downloaded)).await?;
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // A partial file as long as the remote one was interrupted before its rename
+        let total = unsatisfied_range_total(&response).or(expected.map(|e| e.size));
+        if total == Some(downloaded) {
+            on_progress(DownloadProgress { downloaded, total });
+            return finish_download(url, &part_path, path, expected).await;
+        }
+
+        // The partial file is longer than the remote one, start over
+        downloaded = 0;
+        response = with_timeout(url, read_timeout, send(client, url, downloaded)).await?;
+    }
+
+    let status = response.status();
+    if !status.is_success() {
+        bail!("download {url} failed: HTTP {status}");
+    }
+
+    // An error page is not a model, even with a 200 status
+    if let Some(content_type) = response.headers().get(CONTENT_TYPE) {
+        if content_type
+            .to_str()
+            .unwrap_or_default()
+            .starts_with("text/html")
+        {
+            bail!("download {url} failed: got an HTML page instead of the file");
+        }
+    }
+
+    let resumed =
+        status == StatusCode::PARTIAL_CONTENT && content_range_start(&response) == Some(downloaded);
+    if !resumed {
+        // The server ignored the Range header and sends the whole file
+        downloaded = 0;
+    }
+
+    let total = match resumed {
+        true => content_range_total(&response),
+        false => response.content_length(),
+    };
+
+    let mut file = if resumed {
+        OpenOptions::new().append(true).open(&part_path).await?
     } else {
-        Client::new()
+        File::create(&part_path).await?
     };
 
-    let response = client.get(&url).send().await?;
+    on_progress(DownloadProgress { downloaded, total });
+    while let Some(chunk) = with_timeout(url, read_timeout, response.chunk()).await? {
+        file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+        on_progress(DownloadProgress { downloaded, total });
+    }
+    file.flush().await?;
+    file.sync_all().await?;
+    drop(file);
 
-    let mut file = std::fs::File::create(format!("{}/{}", models_dir, model_name))?;
-    let mut content = std::io::Cursor::new(response.bytes().await?);
-    std::io::copy(&mut content, &mut file)?;
-    Ok(())
+    if let Some(total) = total {
+        if downloaded != total {
+            bail!("download {url} incomplete: {downloaded} of {total} bytes");
+        }
+    }
+
+    finish_download(url, &part_path, path, expected).await
+}
+
+// Checks the complete partial file against `expected` and renames it to `path`
+async fn finish_download(
+    url: &str,
+    part_path: &Path,
+    path: &Path,
+    expected: Option<&Checksum>,
+) -> Result<Checksum> {
+    let part = part_path.to_path_buf();
+    let checksum = tokio::task::spawn_blocking(move || Checksum::of_file(&part)).await??;
+    if let Some(expected) = expected {
+        if checksum.size != expected.size || !checksum.sha256.eq_ignore_ascii_case(&expected.sha256)
+        {
+            fs::remove_file(part_path).await?;
+            bail!(
+                "download {url} is corrupt: expected sha256 {}, got {}",
+                expected.sha256,
+                checksum.sha256
+            );
+        }
+    }
+
+    fs::rename(part_path, path).await?;
+    Ok(checksum)
+}
+
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(PART_SUFFIX);
+    PathBuf::from(part)
+}
+
+/// Removes the partial download of the model at `path`, unless it is being downloaded,
+/// possibly by another process.
+pub fn remove_part(path: &Path) -> Result<()> {
+    let _lock = DownloadLock::acquire(path)?;
+    match std::fs::remove_file(part_path(path)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+// Held while a model downloads, so that two downloads, in this process or another one,
+// never write to the same partial file. The OS releases it if the process dies.
+struct DownloadLock {
+    path: PathBuf,
+    file: Option<std::fs::File>,
+}
+
+impl DownloadLock {
+    fn acquire(path: &Path) -> Result<Self> {
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(format!(".lock{PART_SUFFIX}"));
+        let lock_path = PathBuf::from(lock_path);
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(Self {
+                path: lock_path,
+                file: Some(file),
+            }),
+            Err(std::fs::TryLockError::WouldBlock) => {
+                bail!("{} is already being downloaded", path.display())
+            }
+            Err(std::fs::TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+}
+
+impl Drop for DownloadLock {
+    fn drop(&mut self) {
+        // Closed first, Windows does not remove an open file
+        drop(self.file.take());
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn send(client: &Client, url: &str, offset: u64) -> reqwest::Result<Response> {
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={offset}-"));
+    }
+    request.send().await
+}
+
+async fn with_timeout<T>(
+    url: &str,
+    timeout: Option<Duration>,
+    future: impl Future<Output = reqwest::Result<T>>,
+) -> Result<T> {
+    match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, future).await {
+            Ok(result) => Ok(result?),
+            Err(_) => bail!("download {url} timed out: nothing received for {timeout:?}"),
+        },
+        None => Ok(future.await?),
+    }
+}
+
+// "bytes 100-199/200"
+fn content_range(response: &Response) -> Option<(u64, Option<u64>)> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.parse().ok()?;
+    Some((start, total.parse().ok()))
+}
+
+// "bytes */200", the size of the file in a 416 response
+fn unsatisfied_range_total(response: &Response) -> Option<u64> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    value.strip_prefix("bytes */")?.parse().ok()
+}
+
+fn content_range_start(response: &Response) -> Option<u64> {
+    content_range(response).map(|(start, _)| start)
+}
+
+fn content_range_total(response: &Response) -> Option<u64> {
+    content_range(response).and_then(|(_, total)| total)
 }
 
 #[cfg(test)]
@@ -109,7 +470,9 @@ mod tests {
 
         let model_handler = ModelHandler::new("ggml-tiny.bin", "test_dir/").unwrap();
 
-        let _result = model_handler.download_model(None).await;
+        let _result = model_handler
+            .download_model(&DownloadOptions::default())
+            .await;
 
         let is_file_existing = match std::fs::metadata("test_dir/ggml-tiny.bin") {
             Ok(_) => true,
@@ -120,4 +483,264 @@ mod tests {
 
         let _ = std::fs::remove_dir_all("test_dir/");
     }
+
+    // Serves `body` to every connection, honouring "Range: bytes=N-" with a 416 past its
+    // end, or a 404 page
+    async fn serve(body: &'static [u8], found: bool) -> String {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+                let start = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("range: bytes="))
+                    .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+
+                let head = match (found, start) {
+                    (false, _) => "HTTP/1.1 404 Not Found\r\nContent-Type: text/html".to_string(),
+                    (true, Some(start)) if start >= body.len() => format!(
+                        "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{}",
+                        body.len()
+                    ),
+                    (true, Some(start)) => format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {start}-{}/{}",
+                        body.len() - 1,
+                        body.len()
+                    ),
+                    (true, None) => "HTTP/1.1 200 OK".to_string(),
+                };
+                let content: &[u8] = match (found, start) {
+                    (false, _) => b"<html>not found</html>",
+                    (true, Some(start)) if start >= body.len() => b"",
+                    (true, start) => &body[start.unwrap_or(0)..],
+                };
+
+                let response = format!(
+                    "{head}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    content.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.write_all(content).await;
+            }
+        });
+
+        format!("http://{addr}/model.bin")
+    }
+
+    #[test]
+    fn test_download_lock() -> Result<()> {
+        let dir = std::env::temp_dir().join("transcribe-test-download-lock");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+
+        let path = dir.join("model.bin");
+        std::fs::write(part_path(&path), "01234")?;
+
+        let lock = DownloadLock::acquire(&path)?;
+        assert!(DownloadLock::acquire(&path).is_err());
+        assert!(remove_part(&path).is_err());
+        assert!(part_path(&path).exists());
+
+        drop(lock);
+        remove_part(&path)?;
+        assert!(!part_path(&path).exists());
+        assert_eq!(std::fs::read_dir(&dir)?.count(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_file_resume() -> Result<()> {
+        let url = serve(b"0123456789", true).await;
+        let dir = std::env::temp_dir().join("transcribe-test-download-resume");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+
+        let path = dir.join("model.bin");
+        std::fs::write(part_path(&path), "01234")?;
+
+        let mut progress = vec![];
+        let checksum = download_file(&Client::new(), &url, &path, None, None, |p| {
+            progress.push(p)
+        })
+        .await?;
+
+        assert_eq!(std::fs::read(&path)?, b"0123456789");
+        assert_eq!(checksum, Checksum::of_file(&path)?);
+        assert!(!part_path(&path).exists());
+        assert_eq!(
+            progress.first(),
+            Some(&DownloadProgress {
+                downloaded: 5,
+                total: Some(10)
+            })
+        );
+        assert_eq!(progress.last().and_then(|p| p.fraction()), Some(1.0));
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_file_complete_part() -> Result<()> {
+        let url = serve(b"0123456789", true).await;
+        let dir = std::env::temp_dir().join("transcribe-test-download-complete-part");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+
+        // Complete but not renamed, it is installed without downloading it again
+        let path = dir.join("model.bin");
+        std::fs::write(part_path(&path), "0123456789")?;
+
+        let mut progress = vec![];
+        download_file(&Client::new(), &url, &path, None, None, |p| {
+            progress.push(p)
+        })
+        .await?;
+        assert_eq!(std::fs::read(&path)?, b"0123456789");
+        assert!(!part_path(&path).exists());
+        assert_eq!(
+            progress,
+            vec![DownloadProgress {
+                downloaded: 10,
+                total: Some(10)
+            }]
+        );
+
+        // Longer than the remote file, it is downloaded again
+        std::fs::remove_file(&path)?;
+        std::fs::write(part_path(&path), "0123456789AB")?;
+        download_file(&Client::new(), &url, &path, None, None, |_| ()).await?;
+        assert_eq!(std::fs::read(&path)?, b"0123456789");
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_file_not_found() -> Result<()> {
+        let url = serve(b"", false).await;
+        let dir = std::env::temp_dir().join("transcribe-test-download-not-found");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+
+        let path = dir.join("model.bin");
+        assert!(
+            download_file(&Client::new(), &url, &path, None, None, |_| ())
+                .await
+                .is_err()
+        );
+        assert!(!path.exists());
+        assert!(!part_path(&path).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_file_checksum_mismatch() -> Result<()> {
+        let url = serve(b"0123456789", true).await;
+        let dir = std::env::temp_dir().join("transcribe-test-download-mismatch");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+
+        let path = dir.join("model.bin");
+        let expected = Checksum {
+            size: 10,
+            sha256: "0".repeat(64),
+        };
+        let result =
+            download_file(&Client::new(), &url, &path, Some(&expected), None, |_| ()).await;
+        assert!(result.is_err());
+        assert!(!path.exists());
+        assert!(!part_path(&path).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_download_options_urls() {
+        let url = format!("{HUGGINGFACE_URL}/ggerganov/whisper.cpp/resolve/main/ggml-base.bin");
+        let mut options = DownloadOptions::default();
+        assert_eq!(options.urls(&url), vec![url.clone()]);
+
+        options.mirrors = vec!["https://hf-mirror.com/".into(), HUGGINGFACE_URL.into()];
+        assert_eq!(
+            options.urls(&url),
+            vec![
+                "https://hf-mirror.com/ggerganov/whisper.cpp/resolve/main/ggml-base.bin"
+                    .to_string(),
+                url.clone()
+            ]
+        );
+        assert_eq!(
+            options.urls("http://example.com/model.bin"),
+            vec!["http://example.com/model.bin".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_model_mirror_fallback() -> Result<()> {
+        let missing = serve(b"", false).await;
+        let found = serve(b"0123456789", true).await;
+        let dir = std::env::temp_dir().join("transcribe-test-download-fallback");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+
+        let options = DownloadOptions {
+            mirrors: [missing, found]
+                .iter()
+                .map(|url| url.trim_end_matches("/model.bin").to_string())
+                .collect(),
+            read_timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let expected = Checksum {
+            size: 10,
+            sha256: "84d89877f0d4041efb6bf91a16f0248f2fd573e6af05c19f96bedb9f882f7882".into(),
+        };
+        let checksum = download_model_with_progress(
+            dir.to_str().unwrap(),
+            "model.bin",
+            &format!("{HUGGINGFACE_URL}/model.bin"),
+            &options,
+            Some(expected.clone()),
+            |_| (),
+        )
+        .await?;
+
+        assert_eq!(checksum, expected);
+        assert_eq!(std::fs::read(dir.join("model.bin"))?, b"0123456789");
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_file_read_timeout() -> Result<()> {
+        // Accepts the connections and never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/model.bin", listener.local_addr()?);
+        tokio::spawn(async move {
+            let mut streams = vec![];
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
+        });
+
+        let path = std::env::temp_dir().join("transcribe-test-download-timeout.bin");
+        let timeout = Some(Duration::from_millis(200));
+        let result = download_file(&Client::new(), &url, &path, None, timeout, |_| ()).await;
+        assert!(format!("{:?}", result.err()).contains("timed out"));
+        assert!(!path.exists());
+        Ok(())
+    }
 }
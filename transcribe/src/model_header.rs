@@ -0,0 +1,325 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// "ggml" read as a little endian u32, the magic of the whisper.cpp models.
+pub const GGML_MAGIC: u32 = 0x67676d6c;
+/// "GGUF", the magic of the newer ggml format, which whisper.cpp does not load.
+pub const GGUF_MAGIC: u32 = 0x46554747;
+
+// The multilingual models have the language tokens on top of the English vocabulary
+const MULTILINGUAL_VOCAB: i32 = 51865;
+
+// The quantization version is stored in the thousands of ftype
+const QNT_VERSION_FACTOR: i32 = 1000;
+
+/// The hyperparameters that follow the magic of a whisper.cpp model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WhisperHparams {
+    pub n_vocab: i32,
+    pub n_audio_ctx: i32,
+    pub n_audio_state: i32,
+    pub n_audio_head: i32,
+    pub n_audio_layer: i32,
+    pub n_text_ctx: i32,
+    pub n_text_state: i32,
+    pub n_text_head: i32,
+    pub n_text_layer: i32,
+    pub n_mels: i32,
+    pub ftype: i32,
+}
+
+impl WhisperHparams {
+    pub fn is_multilingual(&self) -> bool {
+        self.n_vocab >= MULTILINGUAL_VOCAB
+    }
+
+    /// Such as "f16" or "q5_0", `None` for an unknown type.
+    pub fn quantization(&self) -> Option<&'static str> {
+        match self.ftype % QNT_VERSION_FACTOR {
+            0 => Some("f32"),
+            1 => Some("f16"),
+            2 => Some("q4_0"),
+            3 => Some("q4_1"),
+            4 => Some("q4_1_f16"),
+            7 => Some("q8_0"),
+            8 => Some("q5_0"),
+            9 => Some("q5_1"),
+            10 => Some("q2_k"),
+            11 => Some("q3_k"),
+            12 => Some("q4_k"),
+            13 => Some("q5_k"),
+            14 => Some("q6_k"),
+            _ => None,
+        }
+    }
+
+    /// The size of the encoder, like whisper.cpp names it. The turbo models have the
+    /// encoder of large with 4 decoder layers.
+    pub fn model_type(&self) -> &'static str {
+        match (self.n_audio_layer, self.n_text_layer) {
+            (4, _) => "tiny",
+            (6, _) => "base",
+            (12, _) => "small",
+            (24, _) => "medium",
+            (32, 4) => "large-turbo",
+            (32, _) => "large",
+            _ => "unknown",
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let sizes = [
+            self.n_vocab,
+            self.n_audio_ctx,
+            self.n_audio_state,
+            self.n_audio_head,
+            self.n_audio_layer,
+            self.n_text_ctx,
+            self.n_text_state,
+            self.n_text_head,
+            self.n_text_layer,
+            self.n_mels,
+        ];
+        if sizes.iter().any(|size| *size <= 0 || *size > 1 << 20) {
+            bail!("invalid whisper hyperparameters: {self:?}");
+        }
+
+        if self.quantization().is_none() {
+            bail!("unknown ggml file type {}", self.ftype);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum ModelHeader {
+    Ggml(WhisperHparams),
+    Gguf {
+        version: u32,
+        tensor_count: u64,
+        /// Such as "llama", from the "general.architecture" metadata.
+        architecture: Option<String>,
+    },
+}
+
+impl ModelHeader {
+    /// Reads the header at the start of the file.
+    pub fn read(path: &Path) -> Result<ModelHeader> {
+        let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+        ModelHeader::parse(&mut BufReader::new(file))
+            .with_context(|| format!("{} is not a whisper model", path.display()))
+    }
+
+    pub fn parse(reader: &mut impl Read) -> Result<ModelHeader> {
+        let magic = read_u32(reader).context("the file is too short")?;
+        match magic {
+            GGML_MAGIC => {
+                // The fields are read in the order they are written
+                let mut next = || -> Result<i32> {
+                    Ok(read_u32(reader).context("truncated ggml header")? as i32)
+                };
+                let hparams = WhisperHparams {
+                    n_vocab: next()?,
+                    n_audio_ctx: next()?,
+                    n_audio_state: next()?,
+                    n_audio_head: next()?,
+                    n_audio_layer: next()?,
+                    n_text_ctx: next()?,
+                    n_text_state: next()?,
+                    n_text_head: next()?,
+                    n_text_layer: next()?,
+                    n_mels: next()?,
+                    ftype: next()?,
+                };
+                hparams.validate()?;
+                Ok(ModelHeader::Ggml(hparams))
+            }
+
+            GGUF_MAGIC => parse_gguf(reader).context("invalid gguf header"),
+
+            _ => bail!("unknown magic {magic:#010x}, a ggml model starts with {GGML_MAGIC:#010x}"),
+        }
+    }
+
+    /// The hyperparameters of a model whisper.cpp can load.
+    pub fn whisper(&self) -> Result<&WhisperHparams> {
+        match self {
+            ModelHeader::Ggml(hparams) => Ok(hparams),
+            ModelHeader::Gguf { architecture, .. } => bail!(
+                "this is a GGUF model ({}), whisper.cpp only loads ggml models",
+                architecture.as_deref().unwrap_or("unknown architecture")
+            ),
+        }
+    }
+}
+
+// Version, tensor count and the metadata until "general.architecture"
+fn parse_gguf(reader: &mut impl Read) -> Result<ModelHeader> {
+    let version = read_u32(reader)?;
+    if version < 2 {
+        // Version 1 had 32 bit counts and lengths
+        bail!("unsupported gguf version {version}");
+    }
+
+    let tensor_count = read_u64(reader)?;
+    let kv_count = read_u64(reader)?;
+
+    let mut architecture = None;
+    for _ in 0..kv_count {
+        let key = read_gguf_string(reader)?;
+        let value_type = read_u32(reader)?;
+        if key == "general.architecture" && value_type == GGUF_TYPE_STRING {
+            architecture = Some(read_gguf_string(reader)?);
+            break;
+        }
+        skip_gguf_value(reader, value_type)?;
+    }
+
+    Ok(ModelHeader::Gguf {
+        version,
+        tensor_count,
+        architecture,
+    })
+}
+
+const GGUF_TYPE_STRING: u32 = 8;
+const GGUF_TYPE_ARRAY: u32 = 9;
+
+fn skip_gguf_value(reader: &mut impl Read, value_type: u32) -> Result<()> {
+    let size = match value_type {
+        0 | 1 | 7 => 1,
+        2 | 3 => 2,
+        4..=6 => 4,
+        10..=12 => 8,
+        GGUF_TYPE_STRING => {
+            read_gguf_string(reader)?;
+            return Ok(());
+        }
+        GGUF_TYPE_ARRAY => {
+            let item_type = read_u32(reader)?;
+            for _ in 0..read_u64(reader)? {
+                skip_gguf_value(reader, item_type)?;
+            }
+            return Ok(());
+        }
+        _ => bail!("unknown gguf value type {value_type}"),
+    };
+
+    std::io::copy(&mut reader.take(size), &mut std::io::sink())?;
+    Ok(())
+}
+
+fn read_gguf_string(reader: &mut impl Read) -> Result<String> {
+    let len = read_u64(reader)?;
+    if len > 1 << 20 {
+        bail!("gguf string of {len} bytes");
+    }
+
+    let mut buf = vec![0; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).to_string())
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ggml_bytes(hparams: [i32; 11]) -> Vec<u8> {
+        let mut bytes = GGML_MAGIC.to_le_bytes().to_vec();
+        for field in hparams {
+            bytes.extend(field.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parse_ggml() -> Result<()> {
+        // ggml-base.bin, then ggml-large-v3-turbo-q5_0.bin
+        let bytes = ggml_bytes([51865, 1500, 512, 8, 6, 448, 512, 8, 6, 80, 1]);
+        let header = ModelHeader::parse(&mut bytes.as_slice())?;
+        let hparams = header.whisper()?;
+        assert_eq!(hparams.n_audio_layer, 6);
+        assert_eq!(hparams.model_type(), "base");
+        assert_eq!(hparams.quantization(), Some("f16"));
+        assert!(hparams.is_multilingual());
+
+        let bytes = ggml_bytes([51866, 1500, 1280, 20, 32, 448, 1280, 20, 4, 128, 2008]);
+        let hparams = *ModelHeader::parse(&mut bytes.as_slice())?.whisper()?;
+        assert_eq!(hparams.model_type(), "large-turbo");
+        assert_eq!(hparams.quantization(), Some("q5_0"));
+        assert_eq!(hparams.n_mels, 128);
+
+        // ggml-tiny.en.bin
+        let bytes = ggml_bytes([51864, 1500, 384, 6, 4, 448, 384, 6, 4, 80, 1]);
+        assert!(!ModelHeader::parse(&mut bytes.as_slice())?
+            .whisper()?
+            .is_multilingual());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        // A JPEG
+        let jpeg = [0xff, 0xd8, 0xff, 0xe0, 0x00, 0x10, b'J', b'F', b'I', b'F'];
+        let e = ModelHeader::parse(&mut jpeg.as_slice()).unwrap_err();
+        assert!(e.to_string().contains("unknown magic"));
+
+        assert!(ModelHeader::parse(&mut [0x6c, 0x6d].as_slice()).is_err());
+
+        let truncated = ggml_bytes([51865, 1500, 512, 8, 6, 448, 512, 8, 6, 80, 1]);
+        assert!(ModelHeader::parse(&mut &truncated[..20]).is_err());
+
+        let bad_ftype = ggml_bytes([51865, 1500, 512, 8, 6, 448, 512, 8, 6, 80, 5]);
+        assert!(ModelHeader::parse(&mut bad_ftype.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_parse_gguf() -> Result<()> {
+        let string = |text: &str| {
+            let mut bytes = (text.len() as u64).to_le_bytes().to_vec();
+            bytes.extend(text.as_bytes());
+            bytes
+        };
+
+        let mut bytes = GGUF_MAGIC.to_le_bytes().to_vec();
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend(291u64.to_le_bytes());
+        bytes.extend(2u64.to_le_bytes());
+        bytes.extend(string("general.tags"));
+        bytes.extend(GGUF_TYPE_ARRAY.to_le_bytes());
+        bytes.extend(GGUF_TYPE_STRING.to_le_bytes());
+        bytes.extend(1u64.to_le_bytes());
+        bytes.extend(string("text-generation"));
+        bytes.extend(string("general.architecture"));
+        bytes.extend(GGUF_TYPE_STRING.to_le_bytes());
+        bytes.extend(string("llama"));
+
+        let header = ModelHeader::parse(&mut bytes.as_slice())?;
+        assert_eq!(
+            header,
+            ModelHeader::Gguf {
+                version: 3,
+                tensor_count: 291,
+                architecture: Some("llama".to_string()),
+            }
+        );
+        assert!(header.whisper().unwrap_err().to_string().contains("llama"));
+        Ok(())
+    }
+}
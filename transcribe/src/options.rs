@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum Sampling {
+    Greedy { best_of: u32 },
+    BeamSearch { beam_size: u32, patience: f32 },
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling::Greedy { best_of: 1 }
+    }
+}
+
+/// Options of one transcription. Missing fields take their default when deserialized, so
+/// stored options keep loading when new ones are added.
+///
+/// ```
+/// use transcribe::options::TranscribeOptions;
+///
+/// let options = TranscribeOptions::new()
+///     .language("zh")
+///     .beam_search(5)
+///     .initial_prompt("以下是普通话的句子。");
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscribeOptions {
+    /// ISO 639-1 code such as "en" or "zh", `None` detects the language from the audio.
+    pub language: Option<String>,
+    /// Translate the speech to English instead of transcribing it.
+    pub translate: bool,
+    /// `None` uses up to 4 of the available cores.
+    pub threads: Option<usize>,
+    pub sampling: Sampling,
+    pub temperature: f32,
+    /// Added to the temperature each time a decoding fails whisper's quality checks, until it
+    /// passes or reaches 1.0. 0 disables the fallback.
+    pub temperature_inc: f32,
+    /// Text that comes before the audio, e.g. to give the spelling of names.
+    pub initial_prompt: Option<String>,
+    /// Do not use the text decoded so far as prompt for the next window.
+    pub no_context: bool,
+    pub suppress_blank: bool,
+    pub suppress_non_speech_tokens: bool,
+    /// Maximum segment length in characters, 0 for no limit. Segments are split on words.
+    pub max_segment_len: usize,
+    /// Where to start in the audio, in milliseconds.
+    pub offset_ms: u64,
+    /// How much audio to transcribe, in milliseconds. 0 goes to the end.
+    pub duration_ms: u64,
+}
+
+impl Default for TranscribeOptions {
+    fn default() -> Self {
+        Self {
+            language: None,
+            translate: false,
+            threads: None,
+            sampling: Sampling::default(),
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            initial_prompt: None,
+            no_context: false,
+            suppress_blank: true,
+            suppress_non_speech_tokens: false,
+            max_segment_len: 0,
+            offset_ms: 0,
+            duration_ms: 0,
+        }
+    }
+}
+
+impl TranscribeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn detect_language(mut self) -> Self {
+        self.language = None;
+        self
+    }
+
+    pub fn translate(mut self, translate: bool) -> Self {
+        self.translate = translate;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    pub fn greedy(mut self, best_of: u32) -> Self {
+        self.sampling = Sampling::Greedy { best_of };
+        self
+    }
+
+    pub fn beam_search(mut self, beam_size: u32) -> Self {
+        self.sampling = Sampling::BeamSearch {
+            beam_size,
+            patience: -1.0,
+        };
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f32, temperature_inc: f32) -> Self {
+        self.temperature = temperature;
+        self.temperature_inc = temperature_inc;
+        self
+    }
+
+    pub fn initial_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.initial_prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn no_context(mut self, no_context: bool) -> Self {
+        self.no_context = no_context;
+        self
+    }
+
+    pub fn suppress_blank(mut self, suppress: bool) -> Self {
+        self.suppress_blank = suppress;
+        self
+    }
+
+    pub fn suppress_non_speech_tokens(mut self, suppress: bool) -> Self {
+        self.suppress_non_speech_tokens = suppress;
+        self
+    }
+
+    pub fn max_segment_len(mut self, max_len: usize) -> Self {
+        self.max_segment_len = max_len;
+        self
+    }
+
+    pub fn range(mut self, offset_ms: u64, duration_ms: u64) -> Self {
+        self.offset_ms = offset_ms;
+        self.duration_ms = duration_ms;
+        self
+    }
+
+    pub fn thread_count(&self) -> usize {
+        match self.threads {
+            Some(threads) => threads.max(1),
+            None => std::thread::available_parallelism().map_or(1, |n| n.get().min(4)),
+        }
+    }
+
+    /// Whisper parameters for these options. The language is left to the caller, it may
+    /// have to be detected first. Offset and duration are applied while streaming the audio.
+    pub(crate) fn full_params(&self) -> whisper_rs::FullParams<'_, '_> {
+        let strategy = match self.sampling {
+            Sampling::Greedy { best_of } => whisper_rs::SamplingStrategy::Greedy {
+                best_of: best_of.max(1) as i32,
+            },
+            Sampling::BeamSearch {
+                beam_size,
+                patience,
+            } => whisper_rs::SamplingStrategy::BeamSearch {
+                beam_size: beam_size.max(1) as i32,
+                patience,
+            },
+        };
+
+        let mut params = whisper_rs::FullParams::new(strategy);
+        params.set_n_threads(self.thread_count() as i32);
+        params.set_translate(self.translate);
+        params.set_temperature(self.temperature);
+        params.set_temperature_inc(self.temperature_inc);
+        params.set_no_context(self.no_context);
+        params.set_suppress_blank(self.suppress_blank);
+        params.set_suppress_non_speech_tokens(self.suppress_non_speech_tokens);
+
+        if self.max_segment_len > 0 {
+            // whisper needs token timestamps to split segments
+            params.set_token_timestamps(true);
+            params.set_max_len(self.max_segment_len as i32);
+            params.set_split_on_word(true);
+        }
+
+        params
+    }
+}
+
+/// Code and English name of every language whisper knows, in whisper's order.
+pub fn languages() -> Vec<(&'static str, &'static str)> {
+    (0..=whisper_rs::get_lang_max_id())
+        .filter_map(|id| {
+            Some((
+                whisper_rs::get_lang_str(id)?,
+                whisper_rs::get_lang_str_full(id)?,
+            ))
+        })
+        .collect()
+}
+
+pub fn is_supported_language(code: &str) -> bool {
+    whisper_rs::get_lang_id(code).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_serde() {
+        let options = TranscribeOptions::new()
+            .language("zh")
+            .beam_search(5)
+            .initial_prompt("prompt")
+            .range(1000, 2000);
+
+        let text = serde_json::to_string(&options).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TranscribeOptions>(&text).unwrap(),
+            options
+        );
+
+        let options: TranscribeOptions = serde_json::from_str(
+            r#"{"language": "en", "sampling": {"strategy": "greedy", "best_of": 3}}"#,
+        )
+        .unwrap();
+        assert_eq!(options.language.as_deref(), Some("en"));
+        assert_eq!(options.sampling, Sampling::Greedy { best_of: 3 });
+        assert!(options.suppress_blank);
+        assert_eq!(options.temperature_inc, 0.2);
+    }
+}
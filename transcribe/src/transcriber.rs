@@ -1,24 +1,129 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_int, c_void};
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
 
-use crate::audio_parser;
+use crate::audio_parser::{
+    self, ms_to_samples, samples_to_ms, AudioConversion, AudioStream, TrackSelector,
+};
+use crate::job::{Aborted, JobControl, Progress};
 use crate::model_handler;
+use crate::options::{self, TranscribeOptions};
+use crate::vad::{SpeechTimeline, VoiceDetector};
 
-#[derive(Debug)]
+// Two minutes of audio per whisper run, whisper itself works on 30 s chunks
+const WINDOW_SIZE: usize = 120 * audio_parser::WHISPER_SAMPLE_RATE as usize;
+const WINDOW_SPLIT_SEARCH: usize = 3 * audio_parser::WHISPER_SAMPLE_RATE as usize;
+
+// Whisper detects the language from the first 30 s it is given
+const LANGUAGE_DETECT_SIZE: usize = 30 * audio_parser::WHISPER_SAMPLE_RATE as usize;
+
+// Silence put between speech regions when only speech is transcribed
+const VAD_GAP: usize = audio_parser::WHISPER_SAMPLE_RATE as usize / 2;
+
+/// One whisper segment. Timestamps are in milliseconds and `token_range` indexes the tokens
+/// of the whole transcription, so consecutive segments have adjacent ranges.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub index: usize,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+    pub text: String,
+    pub token_range: Range<usize>,
+}
+
+/// One text token, special tokens are left out. Timestamps are in milliseconds and only
+/// follow the token when word timestamps are enabled, otherwise they are the segment bounds.
+///
+/// A character encoded over several tokens is put into the text of the token that completes
+/// it, so `text` can be empty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Token {
+    pub id: i32,
+    pub text: String,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+    pub probability: f32,
+}
+
+/// A word built from the tokens in `token_range`. Every CJK character is a word of its own.
+/// `probability` is the mean of the token probabilities.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    pub text: String,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+    pub probability: f32,
+    pub token_range: Range<usize>,
+}
+
+/// The language whisper detected and its probability, from 0 to 1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectedLanguage {
+    pub code: String,
+    pub probability: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriberOutput {
-    start_timestamp: i64,
-    end_timestamp: i64,
+    segments: Vec<Segment>,
+    tokens: Vec<Token>,
     text: String,
+    language: Option<DetectedLanguage>,
+    conversion: Option<AudioConversion>,
 }
 
 impl TranscriberOutput {
-    pub fn get_start_timestamp(&self) -> &i64 {
-        &self.start_timestamp
+    pub fn new(segments: Vec<Segment>, tokens: Vec<Token>) -> TranscriberOutput {
+        let text = segments.iter().map(|s| s.text.as_str()).collect();
+        TranscriberOutput {
+            segments,
+            tokens,
+            text,
+            language: None,
+            conversion: None,
+        }
+    }
+
+    /// Only set when the language was detected rather than given in the options.
+    pub fn get_language(&self) -> Option<&DetectedLanguage> {
+        self.language.as_ref()
+    }
+
+    /// How the source audio was downmixed and resampled before inference.
+    pub fn get_conversion(&self) -> Option<&AudioConversion> {
+        self.conversion.as_ref()
     }
 
-    pub fn get_end_timestamp(&self) -> &i64 {
-        &self.end_timestamp
+    /// Start of the first segment in milliseconds.
+    pub fn get_start_timestamp(&self) -> i64 {
+        self.segments.first().map_or(0, |s| s.start_timestamp)
     }
 
+    /// End of the last segment in milliseconds.
+    pub fn get_end_timestamp(&self) -> i64 {
+        self.segments.last().map_or(0, |s| s.end_timestamp)
+    }
+
+    pub fn get_segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn get_tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// The transcription split on word boundaries. Words never span two segments.
+    pub fn get_words(&self) -> Vec<Word> {
+        self.segments
+            .iter()
+            .flat_map(|s| split_words(&self.tokens, s.token_range.clone()))
+            .collect()
+    }
+
+    /// All segment texts joined together.
     pub fn get_text(&self) -> &str {
         &self.text
     }
@@ -26,6 +131,8 @@ impl TranscriberOutput {
 
 pub struct Transcriber {
     ctx: whisper_rs::WhisperContext,
+    vad: Option<Box<dyn VoiceDetector + Send + Sync>>,
+    word_timestamps: bool,
 }
 
 impl Transcriber {
@@ -36,58 +143,525 @@ impl Transcriber {
                 whisper_rs::WhisperContextParameters::default(),
             )
             .context("failed to load model")?,
+            vad: None,
+            word_timestamps: false,
         })
     }
 
+    /// Only transcribe the speech regions found by `detector`. Silence is skipped, which saves
+    /// time and stops whisper from hallucinating text in long pauses. Timestamps still refer
+    /// to the original audio.
+    pub fn with_vad<D>(mut self, detector: D) -> Self
+    where
+        D: VoiceDetector + Send + Sync + 'static,
+    {
+        self.vad = Some(Box::new(detector));
+        self
+    }
+
+    /// Let whisper time every token, which is needed for word-level timestamps. It is off by
+    /// default because it makes the decoding a bit slower.
+    pub fn with_word_timestamps(mut self, enabled: bool) -> Self {
+        self.word_timestamps = enabled;
+        self
+    }
+
     pub fn transcribe(
         &self,
         audio_path: &str,
-        whisper_params: Option<whisper_rs::FullParams>,
+        options: &TranscribeOptions,
+    ) -> Result<TranscriberOutput> {
+        self.transcribe_track(
+            audio_path,
+            &TrackSelector::Auto,
+            options,
+            &JobControl::default(),
+        )
+    }
+
+    /// Like `transcribe`, reporting progress to `control` and stopping with an `Aborted`
+    /// error when its abort handle is triggered.
+    pub fn transcribe_with_control(
+        &self,
+        audio_path: &str,
+        options: &TranscribeOptions,
+        control: &JobControl,
+    ) -> Result<TranscriberOutput> {
+        self.transcribe_track(audio_path, &TrackSelector::Auto, options, control)
+    }
+
+    /// Runs `transcribe_with_control` on tokio's blocking thread pool. Inference keeps a
+    /// thread busy for minutes, which must not be one of the runtime's async workers.
+    /// With `JobControl::with_slots` the job first waits for a free slot.
+    pub async fn transcribe_async(
+        self: Arc<Self>,
+        audio_path: impl Into<String>,
+        options: TranscribeOptions,
+        control: JobControl,
+    ) -> Result<TranscriberOutput> {
+        let audio_path = audio_path.into();
+        let permit = control.acquire_slot().await?;
+
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            self.transcribe_with_control(&audio_path, &options, &control)
+        })
+        .await
+        .context("transcription task failed")?
+    }
+
+    /// Transcribes the selected audio track, e.g. one language of a multi-track video.
+    ///
+    /// The audio is decoded as a stream and fed to whisper window by window, so memory use
+    /// stays flat for multi-hour recordings. All windows share one whisper state, which keeps
+    /// the decoded text of the previous window as prompt for the next one.
+    pub fn transcribe_track(
+        &self,
+        audio_path: &str,
+        track: &TrackSelector,
+        options: &TranscribeOptions,
+        control: &JobControl,
     ) -> Result<TranscriberOutput> {
-        let audio_data = audio_parser::parse_audio_file(audio_path)
+        if let Some(language) = &options.language {
+            if !options::is_supported_language(language) {
+                bail!("unsupported language: {language}");
+            }
+        }
+
+        let mut stream = AudioStream::open(audio_path, track, audio_parser::STREAM_CHUNK_SIZE)
             .with_context(|| format!("can't not find {audio_path}"))?;
 
         let mut state: whisper_rs::WhisperState =
             self.ctx.create_state().context("Failed to create state")?;
-        let params: whisper_rs::FullParams = match whisper_params {
-            Some(whisper_params) => whisper_params,
-            None => {
-                whisper_rs::FullParams::new(whisper_rs::SamplingStrategy::Greedy { best_of: 1 })
+        let mut params = options.full_params();
+        if self.word_timestamps {
+            params.set_token_timestamps(true);
+        }
+
+        let mut output = TranscriberOutput::new(vec![], vec![]);
+        let mut window: Vec<f32> =
+            Vec::with_capacity(WINDOW_SIZE + audio_parser::STREAM_CHUNK_SIZE);
+        let range_start = ms_to_samples(options.offset_ms);
+        let range_end = match options.duration_ms {
+            0 => usize::MAX,
+            duration_ms => range_start + ms_to_samples(duration_ms),
+        };
+        let mut position = 0;
+        let mut window_offset = range_start;
+
+        // Progress is measured against the duration in the container, when it has one
+        let total = match control.has_progress() {
+            true => audio_parser::probe_audio(audio_path)
+                .ok()
+                .and_then(|info| info.duration_ms)
+                .map(|ms| ms_to_samples(ms).min(range_end).saturating_sub(range_start)),
+            false => None,
+        };
+
+        loop {
+            if control.is_aborted() {
+                return Err(Aborted.into());
+            }
+
+            let chunk = match position < range_end {
+                true => stream.next().transpose()?,
+                false => None,
+            };
+            let is_finished = chunk.is_none();
+            if let Some(chunk) = chunk {
+                // Only keep the part of the chunk within offset and duration
+                let start = range_start.saturating_sub(position).min(chunk.len());
+                let end = (range_end - position).min(chunk.len());
+                window.extend_from_slice(&chunk[start..end.max(start)]);
+                position += chunk.len();
+            }
+
+            if window.len() < WINDOW_SIZE && !is_finished {
+                continue;
+            }
+
+            // Cut at a quiet spot so that words are not split between two windows
+            let split = if is_finished {
+                window.len()
+            } else {
+                quiet_split_point(&window[..WINDOW_SIZE], WINDOW_SPLIT_SEARCH)
+            };
+
+            if split > 0 {
+                let window = Window {
+                    samples: &window[..split],
+                    offset_ms: samples_to_ms(window_offset),
+                    done: window_offset - range_start,
+                    total,
+                    control,
+                };
+                self.transcribe_window(&mut state, &params, options, &window, &mut output)?;
+            }
+
+            window.drain(..split);
+            window_offset += split;
+
+            if is_finished {
+                break;
             }
+        }
+
+        control.report(Progress {
+            percent: total.map(|_| 100.0),
+            segment: None,
+        });
+
+        output.text = output.segments.iter().map(|s| s.text.as_str()).collect();
+        output.conversion = stream.conversion();
+        Ok(output)
+    }
+
+    fn transcribe_window(
+        &self,
+        state: &mut whisper_rs::WhisperState,
+        params: &whisper_rs::FullParams,
+        options: &TranscribeOptions,
+        window: &Window,
+        output: &mut TranscriberOutput,
+    ) -> Result<()> {
+        let timeline = match &self.vad {
+            Some(vad) => {
+                // Without regions the detector may have missed the speech, the whole window is
+                // transcribed rather than dropped
+                let regions = vad.detect(window.samples);
+                match regions.is_empty() {
+                    true => None,
+                    false => Some(SpeechTimeline::new(window.samples, &regions, VAD_GAP)),
+                }
+            }
+            None => None,
         };
+        let samples = timeline
+            .as_ref()
+            .map_or(window.samples, |t| t.samples.as_slice());
+
+        // The language is detected once, on the first window with speech, and kept for the
+        // following windows so that the whole file is in one language
+        let language = match &options.language {
+            Some(language) => language.clone(),
+            None if !self.ctx.is_multilingual() => "en".to_string(),
+            None => match &output.language {
+                Some(detected) => detected.code.clone(),
+                None => {
+                    let detected = self.detect_language(state, samples, options.thread_count())?;
+                    let code = detected.code.clone();
+                    output.language = Some(detected);
+                    code
+                }
+            },
+        };
+
+        let mut params = params.clone();
+        params.set_language(Some(&language));
 
+        // Later windows get the text decoded so far as prompt instead
+        if let Some(prompt) = &options.initial_prompt {
+            if output.segments.is_empty() || options.no_context {
+                params.set_initial_prompt(prompt);
+            }
+        }
+
+        // The `_safe` callback setters of whisper-rs keep dangling pointers to their closures,
+        // so the raw callbacks are used. Their user data outlives `full`, the only place they
+        // are called from.
+        unsafe {
+            params.set_abort_callback(Some(abort_trampoline));
+            params.set_abort_callback_user_data(window.control as *const JobControl as *mut c_void);
+
+            if window.control.has_progress() {
+                params.set_progress_callback(Some(progress_trampoline));
+                params.set_progress_callback_user_data(window as *const Window as *mut c_void);
+            }
+        }
+
+        let result = state.full(params, samples);
+        if window.control.is_aborted() {
+            return Err(Aborted.into());
+        }
+        result.context("failed to run the model")?;
+
+        let first_segment = output.segments.len();
+        match &timeline {
+            Some(timeline) => self.append_segments(
+                state,
+                |ms| timeline.original_ms(ms) + window.offset_ms,
+                output,
+            )?,
+            None => self.append_segments(state, |ms| ms + window.offset_ms, output)?,
+        }
+
+        for segment in output.segments[first_segment..].iter() {
+            window.control.report(Progress {
+                percent: window.percent(100),
+                segment: Some(segment.clone()),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn detect_language(
+        &self,
+        state: &mut whisper_rs::WhisperState,
+        samples: &[f32],
+        threads: usize,
+    ) -> Result<DetectedLanguage> {
         state
-            .full(params, &audio_data[..])
-            .context("failed to run the model")?;
+            .pcm_to_mel(&samples[..samples.len().min(LANGUAGE_DETECT_SIZE)], threads)
+            .context("failed to compute the spectrogram")?;
+        let (id, probabilities) = state
+            .lang_detect(0, threads)
+            .context("failed to detect the language")?;
 
-        let mut transcribed_string = "".to_string();
-        let mut start_timestamp = 0;
-        let mut end_timestamp = 0;
+        let code = whisper_rs::get_lang_str(id).context("unknown language id")?;
+        Ok(DetectedLanguage {
+            code: code.to_string(),
+            probability: probabilities.get(id as usize).copied().unwrap_or_default(),
+        })
+    }
 
-        // fetch the results
+    /// Appends the segments and tokens of the last `full` run, `to_original` maps their
+    /// timestamps from the transcribed buffer to the source audio.
+    fn append_segments(
+        &self,
+        state: &whisper_rs::WhisperState,
+        to_original: impl Fn(i64) -> i64,
+        output: &mut TranscriberOutput,
+    ) -> Result<()> {
         let num_segments = state
             .full_n_segments()
             .context("failed to get number of segments")?;
 
+        let mut pending_bytes = vec![];
         for i in 0..num_segments {
-            let segment = state
-                .full_get_segment_text(i)
+            let text = state
+                .full_get_segment_text_lossy(i)
                 .context("failed to get segment")?;
-            start_timestamp = state
-                .full_get_segment_t0(i)
-                .context("failed to get segment start timestamp")?;
-            end_timestamp = state
-                .full_get_segment_t1(i)
-                .context("failed to get segment end timestamp")?;
-            transcribed_string.push_str(&segment);
+
+            // whisper timestamps are in units of 10 ms
+            let start_timestamp = to_original(
+                state
+                    .full_get_segment_t0(i)
+                    .context("failed to get segment start timestamp")?
+                    * 10,
+            );
+            let end_timestamp = to_original(
+                state
+                    .full_get_segment_t1(i)
+                    .context("failed to get segment end timestamp")?
+                    * 10,
+            );
+
+            let num_tokens = state
+                .full_n_tokens(i)
+                .context("failed to get number of tokens")?;
+
+            let first_token = output.tokens.len();
+            for j in 0..num_tokens {
+                let data = state
+                    .full_get_token_data(i, j)
+                    .context("failed to get token data")?;
+
+                // Special tokens such as timestamps and language tags come after end-of-text
+                if data.id >= self.ctx.token_eot() {
+                    continue;
+                }
+
+                let bytes = self
+                    .ctx
+                    .token_to_cstr(data.id)
+                    .context("failed to get token text")?;
+                pending_bytes.extend_from_slice(bytes.to_bytes());
+
+                let (token_start, token_end) = if data.t0 >= 0 && data.t1 >= data.t0 {
+                    (to_original(data.t0 * 10), to_original(data.t1 * 10))
+                } else {
+                    (start_timestamp, end_timestamp)
+                };
+
+                output.tokens.push(Token {
+                    id: data.id,
+                    text: take_utf8(&mut pending_bytes),
+                    start_timestamp: token_start,
+                    end_timestamp: token_end,
+                    probability: data.p,
+                });
+            }
+
+            output.segments.push(Segment {
+                index: output.segments.len(),
+                start_timestamp,
+                end_timestamp,
+                text,
+                token_range: first_token..output.tokens.len(),
+            });
         }
 
-        Ok(TranscriberOutput {
-            start_timestamp,
-            end_timestamp,
-            text: transcribed_string,
+        Ok(())
+    }
+}
+
+/// One window of audio and where it is in the job.
+struct Window<'a> {
+    samples: &'a [f32],
+    offset_ms: i64,
+    // Samples of the job transcribed before this window, and in total
+    done: usize,
+    total: Option<usize>,
+    control: &'a JobControl,
+}
+
+impl Window<'_> {
+    /// Turns whisper's progress within this window into progress of the whole job.
+    fn percent(&self, window_percent: i32) -> Option<f32> {
+        let total = self.total?.max(1) as f32;
+        let window = self.samples.len() as f32 * window_percent.clamp(0, 100) as f32 / 100.0;
+        let done = self.done as f32 + window;
+        Some((done / total * 100.0).min(100.0))
+    }
+}
+
+unsafe extern "C" fn progress_trampoline(
+    _: *mut whisper_rs::WhisperSysContext,
+    _: *mut whisper_rs::WhisperSysState,
+    percent: c_int,
+    user_data: *mut c_void,
+) {
+    let window = &*(user_data as *const Window);
+
+    // A panic must not unwind into whisper
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        window.control.report(Progress {
+            percent: window.percent(percent),
+            segment: None,
         })
+    }));
+}
+
+unsafe extern "C" fn abort_trampoline(user_data: *mut c_void) -> bool {
+    let control = &*(user_data as *const JobControl);
+    control.is_aborted()
+}
+
+/// Returns the end of the quietest 20 ms frame within the last `search` samples.
+fn quiet_split_point(samples: &[f32], search: usize) -> usize {
+    let frame = audio_parser::WHISPER_SAMPLE_RATE as usize / 50;
+    let search_start = samples.len().saturating_sub(search);
+
+    let mut best = (samples.len(), f32::MAX);
+    let mut start = search_start;
+    while start + frame <= samples.len() {
+        let energy: f32 = samples[start..start + frame].iter().map(|s| s * s).sum();
+        if energy < best.1 {
+            best = (start + frame, energy);
+        }
+        start += frame;
     }
+
+    best.0
+}
+
+/// Takes the complete characters out of `pending`. An incomplete character at the end stays
+/// for the next token, invalid bytes are replaced.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(pending) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => pending.len(),
+    };
+
+    let text = String::from_utf8_lossy(&pending[..complete]).into_owned();
+    pending.drain(..complete);
+    text
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF // Hiragana, Katakana
+        | 0x3400..=0x4DBF // CJK Extension A
+        | 0x4E00..=0x9FFF // CJK Unified Ideographs
+        | 0xF900..=0xFAFF // CJK Compatibility Ideographs
+        | 0x20000..=0x2FA1F // CJK Extensions B-F and supplement
+    )
+}
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || matches!(c as u32,
+            0x2010..=0x206F // General Punctuation
+            | 0x3000..=0x303F // CJK Symbols and Punctuation
+            | 0xFF00..=0xFF0F | 0xFF1A..=0xFF20 // Fullwidth punctuation
+        )
+}
+
+/// Groups the tokens in `range` into words. Words are split on whitespace and around CJK
+/// characters, punctuation sticks to the word before it.
+fn split_words(tokens: &[Token], range: Range<usize>) -> Vec<Word> {
+    let mut words: Vec<Word> = vec![];
+    let mut probabilities: Vec<(f32, usize)> = vec![];
+
+    // `open`: the next letter extends the last word, `glue`: punctuation does
+    let (mut open, mut glue) = (false, false);
+
+    for index in range {
+        let token = &tokens[index];
+        let chars: Vec<char> = token.text.chars().collect();
+        let duration = token.end_timestamp - token.start_timestamp;
+
+        for (k, &c) in chars.iter().enumerate() {
+            // Characters share the time of their token evenly
+            let start = token.start_timestamp + duration * k as i64 / chars.len() as i64;
+            let end = token.start_timestamp + duration * (k as i64 + 1) / chars.len() as i64;
+
+            let extend = if c.is_whitespace() {
+                (open, glue) = (false, false);
+                continue;
+            } else if is_cjk(c) {
+                (open, glue) = (false, true);
+                false
+            } else if is_punctuation(c) {
+                let extend = open || glue;
+                open = true;
+                extend
+            } else {
+                let extend = open;
+                (open, glue) = (true, true);
+                extend
+            };
+
+            match words.last_mut() {
+                Some(word) if extend => {
+                    word.text.push(c);
+                    word.end_timestamp = end;
+                    if word.token_range.end <= index {
+                        word.token_range.end = index + 1;
+                        let probability = probabilities.last_mut().unwrap();
+                        probability.0 += token.probability;
+                        probability.1 += 1;
+                    }
+                }
+                _ => {
+                    words.push(Word {
+                        text: c.to_string(),
+                        start_timestamp: start,
+                        end_timestamp: end,
+                        probability: 0.0,
+                        token_range: index..index + 1,
+                    });
+                    probabilities.push((token.probability, 1));
+                }
+            }
+        }
+    }
+
+    for (word, (sum, count)) in words.iter_mut().zip(probabilities) {
+        word.probability = sum / count as f32;
+    }
+    words
 }
 
 #[cfg(test)]
@@ -97,19 +671,135 @@ mod tests {
     use super::*;
 
     #[tokio::test]
+    #[cfg(feature = "mp3")]
     async fn component_test_happy_case() {
         let expected_result = " By what he is said and done, a man judges himself by what he is willing to do, by what he might have said, or might have done, a judgment that is necessarily hapered, but only by the scope and limits of his imagination, but by the ever-changing measure of his doubt and self-esteem.";
 
-        let tiny_model_handler = model_handler::ModelHandler::new("ggml-tiny.bin", "models").unwrap();
+        let tiny_model_handler =
+            model_handler::ModelHandler::new("ggml-tiny.bin", "models").unwrap();
         let whisper_wrp = Transcriber::new(tiny_model_handler).unwrap();
 
         let result = whisper_wrp
-            .transcribe("src/test_data/test.mp3", None)
+            .transcribe("src/test_data/test.mp3", &TranscribeOptions::default())
             .unwrap();
         let result_text = result.get_text();
 
         assert_eq!(expected_result, result_text);
 
+        let segments = result.get_segments();
+        assert!(!segments.is_empty());
+        assert_eq!(
+            segments.iter().map(|s| s.text.as_str()).collect::<String>(),
+            result_text
+        );
+        assert_eq!(result.get_language().unwrap().code, "en");
+        for pair in segments.windows(2) {
+            assert!(pair[0].start_timestamp <= pair[1].start_timestamp);
+            assert_eq!(pair[0].token_range.end, pair[1].token_range.start);
+        }
+
+        let words = result.get_words();
+        assert_eq!(words[0].text, "By");
+        assert!(words.iter().all(|w| (0.0..=1.0).contains(&w.probability)));
+
         // let _ = std::fs::remove_dir_all("models/");
     }
+
+    fn token(text: &str, start_timestamp: i64, end_timestamp: i64, probability: f32) -> Token {
+        Token {
+            id: 0,
+            text: text.to_string(),
+            start_timestamp,
+            end_timestamp,
+            probability,
+        }
+    }
+
+    #[test]
+    fn test_split_words() {
+        let tokens = vec![
+            token(" Hel", 0, 100, 0.8),
+            token("lo", 100, 200, 0.6),
+            token(",", 200, 210, 1.0),
+            token(" world", 300, 500, 0.9),
+            token(".", 500, 510, 1.0),
+        ];
+        let words = split_words(&tokens, 0..tokens.len());
+
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[0].text, "Hello,");
+        assert_eq!(
+            (words[0].start_timestamp, words[0].end_timestamp),
+            (25, 210)
+        );
+        assert_eq!(words[0].token_range, 0..3);
+        assert!((words[0].probability - 0.8).abs() < 1e-6);
+        assert_eq!(words[1].text, "world.");
+        assert_eq!(words[1].token_range, 3..5);
+    }
+
+    #[test]
+    fn test_split_words_cjk() {
+        let tokens = vec![
+            token("你好", 0, 200, 0.5),
+            token("", 200, 250, 0.1),
+            token("世界", 250, 450, 0.7),
+            token("。", 450, 460, 1.0),
+            token(" ok", 500, 600, 0.9),
+        ];
+        let words = split_words(&tokens, 0..tokens.len());
+
+        let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(texts, ["你", "好", "世", "界。", "ok"]);
+        assert_eq!(
+            (words[1].start_timestamp, words[1].end_timestamp),
+            (100, 200)
+        );
+        assert_eq!(words[3].token_range, 2..4);
+    }
+
+    #[test]
+    fn test_take_utf8() {
+        let bytes = "你".as_bytes();
+        let mut pending = bytes[..2].to_vec();
+        assert_eq!(take_utf8(&mut pending), "");
+
+        pending.extend_from_slice(&bytes[2..]);
+        pending.extend_from_slice(b"a");
+        assert_eq!(take_utf8(&mut pending), "你a");
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_window_percent() {
+        let control = JobControl::default();
+        let samples = vec![0.0; 200];
+        let window = Window {
+            samples: &samples,
+            offset_ms: 0,
+            done: 100,
+            total: Some(400),
+            control: &control,
+        };
+
+        assert_eq!(window.percent(0), Some(25.0));
+        assert_eq!(window.percent(50), Some(50.0));
+        assert_eq!(window.percent(150), Some(75.0));
+
+        let unknown = Window {
+            total: None,
+            ..window
+        };
+        assert_eq!(unknown.percent(50), None);
+    }
+
+    #[test]
+    fn test_quiet_split_point() {
+        let mut samples = vec![0.5f32; 16000];
+        samples[12000..13000].iter_mut().for_each(|s| *s = 0.0);
+
+        let split = quiet_split_point(&samples, 8000);
+        assert!((12000..=13000).contains(&split), "split at {split}");
+        assert_eq!(quiet_split_point(&samples, 0), samples.len());
+    }
 }
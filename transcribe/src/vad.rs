@@ -0,0 +1,290 @@
+use crate::audio_parser::{ms_to_samples, samples_to_ms};
+
+/// A span of speech in a 16 kHz sample buffer, `end` is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeechRegion {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl SpeechRegion {
+    pub fn start_ms(&self) -> i64 {
+        samples_to_ms(self.start)
+    }
+
+    pub fn end_ms(&self) -> i64 {
+        samples_to_ms(self.end)
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+}
+
+/// Splits 16 kHz mono audio into speech regions. Implement it to plug in a model-based
+/// detector instead of `EnergyVad`.
+pub trait VoiceDetector {
+    fn detect(&self, samples: &[f32]) -> Vec<SpeechRegion>;
+}
+
+/// Voice activity detection from frame energy and zero-crossing rate.
+///
+/// The noise floor is estimated per buffer, so the detector adapts to the recording level.
+/// Frames clearly above the floor are speech, frames slightly above it only count when their
+/// zero-crossing rate looks like speech, which keeps quiet fricatives without picking up hum.
+/// Loud frames are speech whatever the floor, a buffer without quiet frames (speech over music,
+/// a noisy recording) would otherwise be all noise floor.
+#[derive(Debug, Clone)]
+pub struct EnergyVad {
+    pub frame_ms: u32,
+    /// How far above the noise floor a frame has to be to count as speech.
+    pub threshold_db: f32,
+    /// Frames below this level are always silence.
+    pub min_level_db: f32,
+    /// Frames above this level are always speech.
+    pub speech_level_db: f32,
+    /// Zero-crossing rate range of unvoiced speech, in crossings per sample.
+    pub zcr_range: (f32, f32),
+    pub min_speech_ms: u32,
+    /// Pauses shorter than this do not split a region.
+    pub min_silence_ms: u32,
+    /// Kept around every region so that word onsets and endings are not cut.
+    pub padding_ms: u32,
+}
+
+impl Default for EnergyVad {
+    fn default() -> Self {
+        Self {
+            frame_ms: 30,
+            threshold_db: 12.0,
+            min_level_db: -55.0,
+            speech_level_db: -30.0,
+            zcr_range: (0.1, 0.5),
+            min_speech_ms: 250,
+            min_silence_ms: 600,
+            padding_ms: 200,
+        }
+    }
+}
+
+impl VoiceDetector for EnergyVad {
+    fn detect(&self, samples: &[f32]) -> Vec<SpeechRegion> {
+        let frame = ms_to_samples(self.frame_ms.into()).max(1);
+        if samples.len() < frame {
+            return vec![];
+        }
+
+        let frames: Vec<(f32, f32)> = samples
+            .chunks(frame)
+            .map(|f| (level_db(f), zero_crossing_rate(f)))
+            .collect();
+
+        let mut levels: Vec<f32> = frames.iter().map(|f| f.0).collect();
+        levels.sort_by(|a, b| a.total_cmp(b));
+        let noise_floor = levels[levels.len() / 10];
+
+        let is_speech = |(level, zcr): (f32, f32)| {
+            if level < self.min_level_db {
+                false
+            } else if level >= self.speech_level_db || level >= noise_floor + self.threshold_db {
+                true
+            } else {
+                level >= noise_floor + self.threshold_db / 2.0
+                    && zcr >= self.zcr_range.0
+                    && zcr <= self.zcr_range.1
+            }
+        };
+
+        let mut regions: Vec<SpeechRegion> = vec![];
+        let min_silence = ms_to_samples(self.min_silence_ms.into());
+        for (i, item) in frames.into_iter().enumerate() {
+            if !is_speech(item) {
+                continue;
+            }
+
+            let start = i * frame;
+            let end = (start + frame).min(samples.len());
+            match regions.last_mut() {
+                Some(last) if start - last.end < min_silence => last.end = end,
+                _ => regions.push(SpeechRegion { start, end }),
+            }
+        }
+
+        let min_speech = ms_to_samples(self.min_speech_ms.into());
+        let padding = ms_to_samples(self.padding_ms.into());
+        let mut padded: Vec<SpeechRegion> = vec![];
+        for region in regions.into_iter().filter(|r| r.len() >= min_speech) {
+            let region = SpeechRegion {
+                start: region.start.saturating_sub(padding),
+                end: (region.end + padding).min(samples.len()),
+            };
+
+            match padded.last_mut() {
+                Some(last) if region.start <= last.end => last.end = region.end,
+                _ => padded.push(region),
+            }
+        }
+
+        padded
+    }
+}
+
+/// Speech regions glued together with short pauses, and the mapping of the glued timeline
+/// back to the original one.
+#[derive(Debug, Clone, Default)]
+pub struct SpeechTimeline {
+    pub samples: Vec<f32>,
+    pieces: Vec<(usize, SpeechRegion)>,
+}
+
+impl SpeechTimeline {
+    /// `gap` samples of silence are put between regions so whisper still sees a pause.
+    pub fn new(samples: &[f32], regions: &[SpeechRegion], gap: usize) -> SpeechTimeline {
+        let mut timeline = SpeechTimeline::default();
+
+        for region in regions.iter().filter(|r| !r.is_empty()) {
+            if !timeline.samples.is_empty() {
+                timeline.samples.resize(timeline.samples.len() + gap, 0.0);
+            }
+
+            timeline.pieces.push((timeline.samples.len(), *region));
+            timeline
+                .samples
+                .extend_from_slice(&samples[region.start..region.end]);
+        }
+
+        timeline
+    }
+
+    /// Maps a timestamp on the glued timeline to the original one. Timestamps inside a gap
+    /// map to the end of the region before it.
+    pub fn original_ms(&self, ms: i64) -> i64 {
+        let position = ms_to_samples(ms.max(0) as u64);
+
+        let index = self
+            .pieces
+            .partition_point(|(start, _)| *start <= position)
+            .saturating_sub(1);
+
+        match self.pieces.get(index) {
+            Some((start, region)) => {
+                let offset = (position.saturating_sub(*start)).min(region.len());
+                samples_to_ms(region.start + offset)
+            }
+            None => ms,
+        }
+    }
+}
+
+fn level_db(frame: &[f32]) -> f32 {
+    let power = frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32;
+    10.0 * power.max(1e-10).log10()
+}
+
+fn zero_crossing_rate(frame: &[f32]) -> f32 {
+    let crossings = frame
+        .windows(2)
+        .filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0))
+        .count();
+    crossings as f32 / frame.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_parser::WHISPER_SAMPLE_RATE;
+
+    fn tone(seconds: f32) -> Vec<f32> {
+        let n = (seconds * WHISPER_SAMPLE_RATE as f32) as usize;
+        (0..n)
+            .map(|i| 0.3 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 16000.0).sin())
+            .collect()
+    }
+
+    fn silence(seconds: f32) -> Vec<f32> {
+        vec![0.0; (seconds * WHISPER_SAMPLE_RATE as f32) as usize]
+    }
+
+    #[test]
+    fn test_energy_vad() {
+        let samples = [
+            silence(2.0),
+            tone(1.0),
+            silence(2.0),
+            tone(1.5),
+            silence(1.0),
+        ]
+        .concat();
+        let regions = EnergyVad::default().detect(&samples);
+
+        assert_eq!(regions.len(), 2);
+        assert!((regions[0].start_ms() - 1800).abs() <= 30);
+        assert!((regions[0].end_ms() - 3200).abs() <= 30);
+        assert!((regions[1].start_ms() - 4800).abs() <= 30);
+        assert!((regions[1].end_ms() - 6700).abs() <= 30);
+
+        assert!(EnergyVad::default().detect(&silence(3.0)).is_empty());
+    }
+
+    #[test]
+    fn test_energy_vad_merges_short_pauses() {
+        let samples = [
+            silence(1.0),
+            tone(1.0),
+            silence(0.3),
+            tone(1.0),
+            silence(1.0),
+        ]
+        .concat();
+        assert_eq!(EnergyVad::default().detect(&samples).len(), 1);
+    }
+
+    #[test]
+    fn test_energy_vad_continuous_audio() {
+        // No quiet frame to take the noise floor from
+        let samples = tone(5.0);
+        let regions = EnergyVad::default().detect(&samples);
+        assert_eq!(
+            regions,
+            vec![SpeechRegion {
+                start: 0,
+                end: samples.len()
+            }]
+        );
+
+        // Louder speech over quieter music
+        let quiet: Vec<f32> = tone(2.0).iter().map(|s| s * 0.3).collect();
+        let samples = [quiet.clone(), tone(1.0), quiet].concat();
+        let regions = EnergyVad::default().detect(&samples);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].len(), samples.len());
+    }
+
+    #[test]
+    fn test_speech_timeline() {
+        let samples = silence(10.0);
+        let regions = [
+            SpeechRegion {
+                start: 16000,
+                end: 32000,
+            },
+            SpeechRegion {
+                start: 80000,
+                end: 96000,
+            },
+        ];
+
+        let timeline = SpeechTimeline::new(&samples, &regions, 8000);
+        assert_eq!(timeline.samples.len(), 40000);
+
+        assert_eq!(timeline.original_ms(0), 1000);
+        assert_eq!(timeline.original_ms(500), 1500);
+        assert_eq!(timeline.original_ms(1200), 2000);
+        assert_eq!(timeline.original_ms(1500), 5000);
+        assert_eq!(timeline.original_ms(2500), 6000);
+    }
+}
@@ -0,0 +1,113 @@
+use super::print_json;
+use crate::config::{self, Config};
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use serde_json::Value;
+
+// Keys whose value is never printed, an empty one is shown as such
+const SECRET_KEYS: &[&str] = &["/proxy/password"];
+const REDACTED: &str = "******";
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the configuration, or the value of a key such as ui.language
+    Get { key: Option<String> },
+
+    /// Change a key. The value is parsed as JSON, and taken as a string when it is not JSON
+    Set { key: String, value: String },
+}
+
+pub fn run(command: ConfigCommand, json: bool) -> Result<()> {
+    let conf = config::config();
+    let mut root = serde_json::to_value(&conf)?;
+
+    match command {
+        ConfigCommand::Get { key } => {
+            redact(&mut root);
+            let value = match key {
+                Some(key) => root
+                    .pointer(&pointer(&key))
+                    .ok_or_else(|| anyhow!("unknown key: {key}"))?,
+                None => &root,
+            };
+            print_value(value, json)
+        }
+
+        ConfigCommand::Set { key, value } => {
+            let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+            *root
+                .pointer_mut(&pointer(&key))
+                .ok_or_else(|| anyhow!("unknown key: {key}"))? = value.clone();
+
+            // The paths are not serialized, they come from the running config
+            let new_conf = Config {
+                config_path: conf.config_path,
+                db_path: conf.db_path,
+                cache_dir: conf.cache_dir,
+                ..serde_json::from_value(root)
+                    .map_err(|e| anyhow!("invalid value for {key}: {e}"))?
+            };
+            config::save(new_conf)?;
+
+            let mut value = value;
+            if SECRET_KEYS.contains(&pointer(&key).as_str()) {
+                redact_value(&mut value);
+            }
+            print_value(&value, json)
+        }
+    }
+}
+
+// "ui.language" to "/ui/language"
+fn pointer(key: &str) -> String {
+    key.split('.').map(|k| format!("/{k}")).collect()
+}
+
+fn redact(root: &mut Value) {
+    for key in SECRET_KEYS {
+        if let Some(value) = root.pointer_mut(key) {
+            redact_value(value);
+        }
+    }
+}
+
+fn redact_value(value: &mut Value) {
+    if !matches!(value, Value::String(text) if text.is_empty()) {
+        *value = Value::String(REDACTED.to_string());
+    }
+}
+
+fn print_value(value: &Value, json: bool) -> Result<()> {
+    match value {
+        Value::String(text) if !json => println!("{text}"),
+        _ => print_json(value)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointer() {
+        assert_eq!(pointer("ui"), "/ui");
+        assert_eq!(
+            pointer("transcribe.sampling.best_of"),
+            "/transcribe/sampling/best_of"
+        );
+    }
+
+    #[test]
+    fn test_redact() {
+        let mut root = serde_json::json!({"proxy": {"username": "me", "password": "secret"}});
+        redact(&mut root);
+        assert_eq!(root["proxy"]["username"], "me");
+        assert_eq!(root["proxy"]["password"], REDACTED);
+
+        // An unset password is shown as unset
+        let mut root = serde_json::json!({"proxy": {"password": ""}});
+        redact(&mut root);
+        assert_eq!(root["proxy"]["password"], "");
+    }
+}
@@ -0,0 +1,55 @@
+mod conf;
+mod models;
+mod serve;
+mod transcription;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+
+/// Without a command the window is shown.
+#[derive(Parser, Debug)]
+#[command(name = "vtbox", version = crate::version::VERSION, about = "Voice to text toolbox")]
+pub struct Cli {
+    /// Print machine-readable JSON
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Transcribe audio and video files to subtitles or text
+    Transcribe(transcription::TranscribeArgs),
+
+    /// Manage the voice to text models
+    Models {
+        #[command(subcommand)]
+        command: models::ModelsCommand,
+    },
+
+    /// Serve the OpenAI audio transcription API with the local models
+    Serve(serve::ServeArgs),
+
+    /// Read or change the configuration
+    Config {
+        #[command(subcommand)]
+        command: conf::ConfigCommand,
+    },
+}
+
+pub async fn run(command: Command, json: bool) -> Result<()> {
+    match command {
+        Command::Transcribe(args) => transcription::run(args, json).await,
+        Command::Models { command } => models::run(command, json).await,
+        Command::Serve(args) => serve::run(args).await,
+        Command::Config { command } => conf::run(command, json),
+    }
+}
+
+fn print_json(value: &impl Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
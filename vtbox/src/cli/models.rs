@@ -0,0 +1,273 @@
+use super::print_json;
+use crate::logic::model::{
+    catalog_entries, download_model, export_bundle, import_bundle, import_model, installed_models,
+    is_in_catalog, model_attributes, model_status, remove_model, verify_models,
+};
+use crate::{model_cache, util};
+use anyhow::{bail, Result};
+use clap::Subcommand;
+use serde_json::Value;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use transcribe::{manifest::Verification, model_header::ModelHeader};
+
+#[derive(Subcommand, Debug)]
+pub enum ModelsCommand {
+    /// List the downloaded, imported and downloadable models
+    List,
+
+    /// Download a model of the catalog such as ggml-base.bin
+    Download { name: String },
+
+    /// Remove a downloaded or imported model
+    Remove { name: String },
+
+    /// Copy a model file into the model directory
+    Import { file: PathBuf },
+
+    /// Hash the models and compare them with the checksums recorded at download or import
+    Verify,
+
+    /// Show the architecture a model file declares in its header
+    Info { name: String },
+
+    /// Pack models into a tar bundle for the machines without internet, all of the
+    /// downloaded and imported ones when none is named
+    ExportBundle {
+        archive: PathBuf,
+        names: Vec<String>,
+    },
+
+    /// Verify a bundle made by `export-bundle` and install its models
+    ImportBundle { archive: PathBuf },
+}
+
+#[derive(Serialize, Debug)]
+struct BundleResult {
+    name: String,
+    installed_as: String,
+    status: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+struct ModelDetails {
+    #[serde(flatten)]
+    info: ModelInfo,
+    header: ModelHeader,
+}
+
+#[derive(Serialize, Debug)]
+struct VerifyResult {
+    name: String,
+    status: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+struct ModelInfo {
+    name: String,
+    family: Option<String>,
+    languages: Option<&'static str>,
+    quantization: Option<String>,
+    size: Option<u64>,
+    status: &'static str,
+    path: String,
+}
+
+impl ModelInfo {
+    fn new(name: &str) -> Self {
+        let path = model_cache::model_path(name);
+        let attributes = model_attributes(0, name);
+        Self {
+            name: name.to_string(),
+            family: attributes.as_ref().map(|a| a.0.clone()),
+            languages: attributes.as_ref().map(|a| a.1),
+            quantization: attributes.as_ref().map(|a| a.2.clone()),
+            size: fs::metadata(&path).ok().map(|m| m.len()),
+            status: model_status(0, name),
+            path,
+        }
+    }
+
+    fn print(&self, json: bool) -> Result<()> {
+        if json {
+            return print_json(self);
+        }
+
+        let size = self.size.map_or("-".to_string(), util::fs::pretty_size);
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.name,
+            self.family.as_deref().unwrap_or("-"),
+            self.languages.unwrap_or("-"),
+            self.quantization.as_deref().unwrap_or("-"),
+            size,
+            self.status
+        );
+        Ok(())
+    }
+}
+
+pub async fn run(command: ModelsCommand, json: bool) -> Result<()> {
+    match command {
+        ModelsCommand::List => {
+            let models = list()?;
+            if json {
+                print_json(&models)?;
+            } else {
+                for model in models.iter() {
+                    model.print(false)?;
+                }
+            }
+        }
+
+        ModelsCommand::Download { name } => {
+            if !is_in_catalog(0, &name) {
+                bail!("{name} can't be downloaded, see `models list` for the available ones");
+            }
+
+            let mut last_percent = None;
+            download_model(0, &name, |progress| {
+                let percent = progress.fraction().map(|f| (f * 100.0) as u32);
+                if json || percent == last_percent {
+                    return;
+                }
+
+                last_percent = percent;
+                match percent {
+                    Some(percent) => eprint!("\rdownloading {name}: {percent}%"),
+                    None => eprint!(
+                        "\rdownloading {name}: {}",
+                        util::fs::pretty_size(progress.downloaded)
+                    ),
+                }
+                let _ = std::io::stderr().flush();
+            })
+            .await?;
+            if !json {
+                eprintln!();
+            }
+            ModelInfo::new(&name).print(json)?;
+        }
+
+        ModelsCommand::Remove { name } => {
+            remove_model(0, &name)?;
+            ModelInfo::new(&name).print(json)?;
+        }
+
+        ModelsCommand::Import { file } => {
+            import_model(0, &file)?;
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            ModelInfo::new(&name).print(json)?;
+        }
+
+        ModelsCommand::Info { name } => {
+            let info = ModelInfo::new(&name);
+            if info.size.is_none() {
+                bail!("{name} is not downloaded");
+            }
+
+            let header = ModelHeader::read(Path::new(&info.path))?;
+            if json {
+                return print_json(&ModelDetails { info, header });
+            }
+
+            info.print(false)?;
+            if let Value::Object(fields) = serde_json::to_value(&header)? {
+                for (key, value) in fields {
+                    match value {
+                        Value::String(text) => println!("{key}\t{text}"),
+                        value => println!("{key}\t{value}"),
+                    }
+                }
+            }
+        }
+
+        ModelsCommand::ExportBundle { archive, names } => {
+            let names = match names.is_empty() {
+                true => installed_models(0)?,
+                false => names,
+            };
+
+            let manifest = export_bundle(0, &names, &archive)?;
+            if json {
+                print_json(&manifest)?;
+            } else {
+                for (name, checksum) in manifest.models.iter() {
+                    println!("{name}\t{}", checksum.sha256);
+                }
+            }
+        }
+
+        ModelsCommand::ImportBundle { archive } => {
+            let (_, imported) = import_bundle(&archive)?;
+            let results: Vec<BundleResult> = imported
+                .into_iter()
+                .map(|model| BundleResult {
+                    status: if model.existed {
+                        "Existing"
+                    } else if model.name != model.installed_as {
+                        "Renamed"
+                    } else {
+                        "Installed"
+                    },
+                    name: model.name,
+                    installed_as: model.installed_as,
+                })
+                .collect();
+
+            if json {
+                print_json(&results)?;
+            } else {
+                for result in results.iter() {
+                    println!(
+                        "{}\t{}\t{}",
+                        result.name, result.installed_as, result.status
+                    );
+                }
+            }
+        }
+
+        ModelsCommand::Verify => {
+            let results: Vec<VerifyResult> = verify_models(0)?
+                .into_iter()
+                .map(|(name, verification)| VerifyResult {
+                    name,
+                    status: match verification {
+                        Verification::Ok => "Ok",
+                        Verification::Mismatch => "Mismatch",
+                        Verification::Unknown => "Unknown",
+                        Verification::Unverified => "Unverified",
+                    },
+                })
+                .collect();
+
+            if json {
+                print_json(&results)?;
+            } else {
+                for result in results.iter() {
+                    println!("{}\t{}", result.name, result.status);
+                }
+            }
+
+            let mismatched = results.iter().filter(|r| r.status == "Mismatch").count();
+            if mismatched > 0 {
+                bail!("{mismatched} models don't match their checksum");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn list() -> Result<Vec<ModelInfo>> {
+    let mut names = installed_models(0)?;
+
+    for entry in catalog_entries(0) {
+        if !names.contains(&entry.name) {
+            names.push(entry.name);
+        }
+    }
+
+    Ok(names.iter().map(|name| ModelInfo::new(name)).collect())
+}
@@ -0,0 +1,525 @@
+use crate::logic::model::model_relative_path;
+use crate::{
+    config, model_cache,
+    transcribe::{
+        audio_parser,
+        export::{self, SubtitleFormat},
+        job::{AbortHandle, JobControl},
+        options,
+        transcriber::TranscriberOutput,
+    },
+};
+use anyhow::Result;
+use axum::{
+    extract::{multipart::Field, DefaultBodyLimit, Multipart, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use clap::Args;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+// Model name the OpenAI clients send by default
+const OPENAI_MODEL: &str = "whisper-1";
+
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(short, long, default_value = "127.0.0.1:8080")]
+    listen: String,
+
+    /// Model used for whisper-1 and requests without a model, the last used model by default
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Jobs transcribed at the same time, the other requests wait for their turn
+    #[arg(short, long, default_value_t = 1)]
+    concurrency: usize,
+
+    /// Largest accepted upload in MB
+    #[arg(long, default_value_t = 1024)]
+    max_upload_mb: usize,
+}
+
+struct ServerState {
+    default_model: String,
+    // Shared by the requests, see `JobControl::with_slots`
+    slots: Arc<Semaphore>,
+}
+
+impl ServerState {
+    // Accepts the file name of a local model, its short name such as "base", or whisper-1
+    fn model_path(&self, model: Option<&str>) -> Result<String, ApiError> {
+        let names = match model {
+            None | Some("") | Some(OPENAI_MODEL) => vec![self.default_model.clone()],
+            Some(name) => vec![name.to_string(), format!("ggml-{name}.bin")],
+        };
+
+        names
+            .iter()
+            .map(|name| model_cache::model_path(name))
+            .find(|path| Path::new(path).is_file())
+            .ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::NOT_FOUND,
+                    format!("model {} is not downloaded", names[0]),
+                )
+            })
+    }
+}
+
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{e:?}"))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let error_type = match self.status.is_client_error() {
+            true => "invalid_request_error",
+            false => "server_error",
+        };
+
+        let body = json!({
+            "error": {
+                "message": self.message,
+                "type": error_type,
+                "param": null,
+                "code": null,
+            }
+        });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResponseFormat {
+    Json,
+    Text,
+    Srt,
+    Vtt,
+    VerboseJson,
+}
+
+impl ResponseFormat {
+    fn parse(format: Option<&str>) -> Result<Self, ApiError> {
+        match format.unwrap_or("json") {
+            "json" => Ok(ResponseFormat::Json),
+            "text" => Ok(ResponseFormat::Text),
+            "srt" => Ok(ResponseFormat::Srt),
+            "vtt" => Ok(ResponseFormat::Vtt),
+            "verbose_json" => Ok(ResponseFormat::VerboseJson),
+            format => Err(ApiError::bad_request(format!(
+                "unsupported response_format: {format}"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TranscriptionRequest {
+    file: Option<Upload>,
+    model: Option<String>,
+    language: Option<String>,
+    prompt: Option<String>,
+    response_format: Option<String>,
+    temperature: Option<f32>,
+}
+
+// The upload is written to a file for the decoder, which is removed with it
+#[derive(Debug)]
+struct Upload(PathBuf);
+
+impl Upload {
+    // Streams the field into the file, so that a large upload is never held in memory
+    async fn save(mut field: Field<'_>) -> Result<Self, ApiError> {
+        // The decoder picks the format from the extension
+        let extension: String = Path::new(field.file_name().unwrap_or_default())
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect();
+
+        let upload =
+            Self(std::env::temp_dir().join(format!("vtbox-{}.{extension}", Uuid::new_v4())));
+        let mut file = tokio::fs::File::create(&upload.0)
+            .await
+            .map_err(anyhow::Error::from)?;
+
+        while let Some(chunk) = field
+            .chunk()
+            .await
+            .map_err(|e| ApiError::bad_request(e.to_string()))?
+        {
+            file.write_all(&chunk).await.map_err(anyhow::Error::from)?;
+        }
+        file.flush().await.map_err(anyhow::Error::from)?;
+
+        Ok(upload)
+    }
+}
+
+impl Drop for Upload {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+// Stops the transcription when the client goes away and the request is dropped
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+pub async fn run(args: ServeArgs) -> Result<()> {
+    let default_model = match args.model {
+        Some(name) => name,
+        None => match config::model().last_used {
+            name if name.is_empty() => "ggml-base.bin".to_string(),
+            name => name,
+        },
+    };
+
+    let state = ServerState {
+        default_model,
+        slots: Arc::new(Semaphore::new(args.concurrency.max(1))),
+    };
+
+    let listener = tokio::net::TcpListener::bind(&args.listen).await?;
+    eprintln!("listening on http://{}", listener.local_addr()?);
+
+    axum::serve(listener, router(state, args.max_upload_mb * 1024 * 1024)).await?;
+    Ok(())
+}
+
+fn router(state: ServerState, max_upload: usize) -> Router {
+    Router::new()
+        .route("/v1/models", get(models))
+        .route("/v1/audio/transcriptions", post(transcriptions))
+        .route("/v1/audio/translations", post(translations))
+        .layer(DefaultBodyLimit::max(max_upload))
+        .with_state(Arc::new(state))
+}
+
+async fn models() -> Json<Value> {
+    let dir = format!("{}/{}", config::cache_dir(), model_relative_path(0));
+    let mut names: Vec<String> = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                match path.is_file() {
+                    true => Some(path.file_name()?.to_str()?.to_string()),
+                    false => None,
+                }
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+    names.sort();
+
+    let data: Vec<Value> = names
+        .iter()
+        .map(|name| json!({"id": name, "object": "model", "owned_by": "vtbox"}))
+        .collect();
+    Json(json!({"object": "list", "data": data}))
+}
+
+async fn transcriptions(
+    State(state): State<Arc<ServerState>>,
+    multipart: Multipart,
+) -> Result<Response, ApiError> {
+    transcribe(state, multipart, false).await
+}
+
+async fn translations(
+    State(state): State<Arc<ServerState>>,
+    multipart: Multipart,
+) -> Result<Response, ApiError> {
+    transcribe(state, multipart, true).await
+}
+
+async fn read_request(mut multipart: Multipart) -> Result<TranscriptionRequest, ApiError> {
+    let mut request = TranscriptionRequest::default();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError::bad_request(e.to_string()))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        if name == "file" {
+            request.file = Some(Upload::save(field).await?);
+            continue;
+        }
+
+        let value = field
+            .text()
+            .await
+            .map_err(|e| ApiError::bad_request(e.to_string()))?;
+        match name.as_str() {
+            "model" => request.model = Some(value),
+            "language" => request.language = Some(value),
+            "prompt" => request.prompt = Some(value),
+            "response_format" => request.response_format = Some(value),
+            "temperature" => {
+                let temperature = value
+                    .parse()
+                    .map_err(|_| ApiError::bad_request(format!("invalid temperature: {value}")))?;
+                request.temperature = Some(temperature);
+            }
+            _ => (),
+        }
+    }
+
+    Ok(request)
+}
+
+async fn transcribe(
+    state: Arc<ServerState>,
+    multipart: Multipart,
+    translate: bool,
+) -> Result<Response, ApiError> {
+    let request = read_request(multipart).await?;
+    let format = ResponseFormat::parse(request.response_format.as_deref())?;
+    let model_path = state.model_path(request.model.as_deref())?;
+    let upload = request
+        .file
+        .ok_or_else(|| ApiError::bad_request("file is required"))?;
+
+    let mut options = config::transcribe();
+    options.translate = translate;
+    options.language = None;
+    if let Some(language) = request.language.filter(|l| !l.is_empty()) {
+        if !options::is_supported_language(&language) {
+            return Err(ApiError::bad_request(format!(
+                "unsupported language: {language}"
+            )));
+        }
+        options.language = Some(language);
+    }
+    if let Some(prompt) = request.prompt.filter(|p| !p.is_empty()) {
+        options.initial_prompt = Some(prompt);
+    }
+    if let Some(temperature) = request.temperature {
+        options.temperature = temperature;
+    }
+
+    let path = upload.0.to_string_lossy().to_string();
+    let duration_ms = tokio::task::spawn_blocking(move || audio_parser::probe_audio(&path))
+        .await
+        .map_err(anyhow::Error::from)?
+        .map_err(|e| ApiError::bad_request(format!("unrecognized audio file: {e}")))?
+        .duration_ms;
+
+    let trans = tokio::task::spawn_blocking(move || model_cache::transcriber(&model_path))
+        .await
+        .map_err(anyhow::Error::from)??;
+
+    let abort = AbortOnDrop(AbortHandle::new());
+    let control = JobControl::new()
+        .with_abort_handle(abort.0.clone())
+        .with_slots(state.slots.clone());
+    let language = options.language.clone();
+    let output = trans
+        .transcribe_async(upload.0.to_string_lossy(), options, control)
+        .await?;
+
+    let task = match translate {
+        true => "translate",
+        false => "transcribe",
+    };
+    render(&output, format, task, language.as_deref(), duration_ms)
+}
+
+fn render(
+    output: &TranscriberOutput,
+    format: ResponseFormat,
+    task: &str,
+    language: Option<&str>,
+    duration_ms: Option<u64>,
+) -> Result<Response, ApiError> {
+    let text = |body: String| ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body);
+
+    let response = match format {
+        ResponseFormat::Json => Json(json!({"text": output.get_text().trim()})).into_response(),
+        ResponseFormat::Text => text(output.get_text().trim().to_string()).into_response(),
+        ResponseFormat::Srt => text(export::render(output, SubtitleFormat::Srt)?).into_response(),
+        ResponseFormat::Vtt => text(export::render(output, SubtitleFormat::Vtt)?).into_response(),
+        ResponseFormat::VerboseJson => {
+            Json(verbose_json(output, task, language, duration_ms)).into_response()
+        }
+    };
+
+    Ok(response)
+}
+
+// OpenAI names the language in English, e.g. "chinese", and the times are in seconds
+fn verbose_json(
+    output: &TranscriberOutput,
+    task: &str,
+    language: Option<&str>,
+    duration_ms: Option<u64>,
+) -> Value {
+    let code = output
+        .get_language()
+        .map(|l| l.code.as_str())
+        .or(language)
+        .unwrap_or_default();
+    let language = options::languages()
+        .into_iter()
+        .find(|(c, _)| *c == code)
+        .map_or(code, |(_, name)| name);
+
+    let duration_ms = duration_ms.unwrap_or(output.get_end_timestamp().max(0) as u64);
+
+    let segments: Vec<Value> = output
+        .get_segments()
+        .iter()
+        .map(|segment| {
+            let tokens: Vec<i32> = output
+                .get_tokens()
+                .get(segment.token_range.clone())
+                .unwrap_or_default()
+                .iter()
+                .map(|token| token.id)
+                .collect();
+
+            json!({
+                "id": segment.index,
+                "start": segment.start_timestamp as f64 / 1000.0,
+                "end": segment.end_timestamp as f64 / 1000.0,
+                "text": segment.text,
+                "tokens": tokens,
+            })
+        })
+        .collect();
+
+    json!({
+        "task": task,
+        "language": language,
+        "duration": duration_ms as f64 / 1000.0,
+        "text": output.get_text().trim(),
+        "segments": segments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcribe::transcriber::{Segment, Token};
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn state() -> ServerState {
+        ServerState {
+            default_model: "ggml-not-downloaded.bin".to_string(),
+            slots: Arc::new(Semaphore::new(1)),
+        }
+    }
+
+    async fn post(uri: &str, fields: &[(&str, &str)]) -> (StatusCode, Value) {
+        let boundary = "vtbox-boundary";
+        let mut body = String::new();
+        for (name, value) in fields {
+            body += &format!("--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"");
+            if *name == "file" {
+                body += "; filename=\"a.wav\"";
+            }
+            body += &format!("\r\n\r\n{value}\r\n");
+        }
+        body += &format!("--{boundary}--\r\n");
+
+        let request = Request::post(uri)
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(Body::from(body))
+            .unwrap();
+
+        let response = router(state(), 1024 * 1024).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_request_errors() {
+        let (status, body) = post(
+            "/v1/audio/transcriptions",
+            &[("file", "RIFF"), ("response_format", "xml")],
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["type"], "invalid_request_error");
+
+        let (status, _) = post(
+            "/v1/audio/translations",
+            &[("file", "RIFF"), ("model", OPENAI_MODEL)],
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_verbose_json() {
+        let segment = Segment {
+            index: 0,
+            start_timestamp: 0,
+            end_timestamp: 1500,
+            text: " Hello".to_string(),
+            token_range: 0..1,
+        };
+        let token = Token {
+            id: 7,
+            text: " Hello".to_string(),
+            start_timestamp: 0,
+            end_timestamp: 1500,
+            probability: 0.9,
+        };
+        let output = TranscriberOutput::new(vec![segment], vec![token]);
+
+        let value = verbose_json(&output, "transcribe", Some("xx"), Some(2000));
+        assert_eq!(value["text"], "Hello");
+        assert_eq!(value["language"], "xx");
+        assert_eq!(value["duration"], 2.0);
+        assert_eq!(value["segments"][0]["end"], 1.5);
+        assert_eq!(value["segments"][0]["tokens"], json!([7]));
+
+        assert_eq!(ResponseFormat::parse(None).unwrap(), ResponseFormat::Json);
+        assert!(ResponseFormat::parse(Some("xml")).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use super::print_json;
+use crate::{
+    config, model_cache,
+    transcribe::{
+        export::{self, SubtitleFormat},
+        job::JobControl,
+        options::{self, TranscribeOptions},
+        transcriber::{Transcriber, TranscriberOutput},
+    },
+    util,
+};
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const DEFAULT_MODEL: &str = "ggml-base.bin";
+
+#[derive(Args, Debug)]
+pub struct TranscribeArgs {
+    /// Audio or video files
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Model file name such as ggml-base.bin, the last used model by default
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Language code such as zh or en, auto detects it
+    #[arg(short, long)]
+    lang: Option<String>,
+
+    /// Translate the speech to English
+    #[arg(long)]
+    translate: bool,
+
+    /// srt, vtt, ass, ssa, lrc, tsv, json or txt
+    #[arg(short, long, default_value = "srt")]
+    format: String,
+
+    /// Output directory, the results are written next to their source by default
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Serialize, Debug)]
+struct FileResult {
+    file: String,
+    output: Option<String>,
+    language: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum OutputFormat {
+    Text,
+    Subtitle(SubtitleFormat),
+}
+
+impl OutputFormat {
+    fn parse(format: &str) -> Result<Self> {
+        if format.eq_ignore_ascii_case("txt") {
+            return Ok(OutputFormat::Text);
+        }
+
+        match SubtitleFormat::from_extension(format) {
+            Some(format) => Ok(OutputFormat::Subtitle(format)),
+            None => bail!("unsupported format: {format}"),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "txt",
+            OutputFormat::Subtitle(format) => format.extension(),
+        }
+    }
+
+    fn save(&self, output: &TranscriberOutput, path: &Path) -> Result<()> {
+        match self {
+            OutputFormat::Text => Ok(fs::write(path, output.get_text())?),
+            OutputFormat::Subtitle(format) => export::save(output, *format, path),
+        }
+    }
+}
+
+pub async fn run(args: TranscribeArgs, json: bool) -> Result<()> {
+    let format = OutputFormat::parse(&args.format)?;
+
+    let model_name = match args.model {
+        Some(name) => name,
+        None => match config::model().last_used {
+            name if name.is_empty() => DEFAULT_MODEL.to_string(),
+            name => name,
+        },
+    };
+    let model_path = model_cache::model_path(&model_name);
+    if !Path::new(&model_path).exists() {
+        bail!("model {model_name} not found, run `vtbox models download {model_name}` first");
+    }
+
+    let mut options = config::transcribe();
+    options.translate = args.translate;
+    match args.lang.as_deref() {
+        Some("auto") => options.language = None,
+        Some(lang) if !options::is_supported_language(lang) => {
+            bail!("unsupported language: {lang}")
+        }
+        Some(lang) => options.language = Some(lang.to_string()),
+        None => (),
+    }
+
+    // Files with the same stem in different directories would write the same output
+    let output_paths: Vec<PathBuf> = args
+        .files
+        .iter()
+        .map(|file| output_path(file, args.output.as_deref(), format))
+        .collect();
+    if let Some(path) = duplicate(&output_paths) {
+        bail!(
+            "several files would be written to {}, transcribe them separately",
+            path.display()
+        );
+    }
+
+    if let Some(dir) = &args.output {
+        fs::create_dir_all(dir).with_context(|| format!("can't create {}", dir.display()))?;
+    }
+
+    let trans =
+        tokio::task::spawn_blocking(move || model_cache::transcriber(&model_path)).await??;
+
+    let mut results = vec![];
+    for (file, output_path) in args.files.iter().zip(output_paths) {
+        let mut result = FileResult {
+            file: file.to_string_lossy().to_string(),
+            output: None,
+            language: None,
+            error: None,
+        };
+
+        match transcribe_file(&trans, file, &output_path, &options, format, json).await {
+            Ok((output, output_path)) => {
+                result.output = Some(output_path.to_string_lossy().to_string());
+                result.language = output.get_language().map(|l| l.code.clone());
+            }
+            Err(e) => result.error = Some(format!("{e:?}")),
+        }
+
+        if !json {
+            match (&result.output, &result.error) {
+                (Some(output), _) => println!("{} -> {output}", result.file),
+                (_, Some(e)) => eprintln!("{}: {e}", result.file),
+                _ => (),
+            }
+        }
+        results.push(result);
+    }
+
+    if json {
+        print_json(&results)?;
+    }
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    if failed > 0 {
+        bail!("{failed} of {} files failed", results.len());
+    }
+    Ok(())
+}
+
+async fn transcribe_file(
+    trans: &Arc<Transcriber>,
+    file: &Path,
+    output_path: &Path,
+    options: &TranscribeOptions,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<(TranscriberOutput, PathBuf)> {
+    let mut control = JobControl::new();
+    if !quiet {
+        let name = file.to_string_lossy().to_string();
+        control = control.on_progress(move |progress| {
+            if let Some(percent) = progress.percent {
+                eprint!("\r{name}: {percent:.0}%");
+                let _ = std::io::stderr().flush();
+            }
+        });
+    }
+
+    let output = trans
+        .clone()
+        .transcribe_async(file.to_string_lossy(), options.clone(), control)
+        .await;
+    if !quiet {
+        eprintln!();
+    }
+
+    // An existing file is kept, the result takes another name
+    let output = output?;
+    let output_path = util::fs::create_new_file(output_path)
+        .with_context(|| format!("can't create {}", output_path.display()))?;
+    if let Err(e) = format.save(&output, &output_path) {
+        let _ = fs::remove_file(&output_path);
+        return Err(e);
+    }
+    Ok((output, output_path))
+}
+
+fn output_path(file: &Path, output_dir: Option<&Path>, format: OutputFormat) -> PathBuf {
+    let name = Path::new(file.file_name().unwrap_or_default()).with_extension(format.extension());
+    match output_dir {
+        Some(dir) => dir.join(name),
+        None => file.with_extension(format.extension()),
+    }
+}
+
+fn duplicate(paths: &[PathBuf]) -> Option<&PathBuf> {
+    paths
+        .iter()
+        .enumerate()
+        .find(|(i, path)| paths[..*i].contains(path))
+        .map(|(_, path)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_path() {
+        let format = OutputFormat::parse("srt").unwrap();
+        assert_eq!(
+            output_path(Path::new("/a/b.mp3"), None, format),
+            PathBuf::from("/a/b.srt")
+        );
+        assert_eq!(
+            output_path(Path::new("/a/b.mp3"), Some(Path::new("out")), format),
+            PathBuf::from("out/b.srt")
+        );
+
+        let paths = [
+            output_path(Path::new("/a/b.mp3"), Some(Path::new("out")), format),
+            output_path(Path::new("/c/b.wav"), Some(Path::new("out")), format),
+        ];
+        assert_eq!(duplicate(&paths), Some(&PathBuf::from("out/b.srt")));
+        assert_eq!(duplicate(&paths[..1]), None);
+
+        let format = OutputFormat::parse("TXT").unwrap();
+        assert_eq!(format.extension(), "txt");
+        assert!(OutputFormat::parse("doc").is_err());
+    }
+}
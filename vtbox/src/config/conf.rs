@@ -1,4 +1,5 @@
 use super::data::{self, Config};
+use crate::transcribe::options::TranscribeOptions;
 use anyhow::Result;
 use log::debug;
 use platform_dirs::AppDirs;
@@ -20,11 +21,26 @@ pub fn ui() -> data::UI {
     CONFIG.lock().unwrap().borrow().ui.clone()
 }
 
-pub fn socks5() -> data::Socks5 {
-    CONFIG.lock().unwrap().borrow().socks5.clone()
+pub fn proxy() -> data::Proxy {
+    CONFIG.lock().unwrap().borrow().proxy.clone()
+}
+
+pub fn download() -> data::Download {
+    CONFIG.lock().unwrap().borrow().download.clone()
+}
+
+pub fn batch() -> data::Batch {
+    CONFIG.lock().unwrap().borrow().batch.clone()
+}
+
+pub fn model() -> data::Model {
+    CONFIG.lock().unwrap().borrow().model.clone()
+}
+
+pub fn transcribe() -> TranscribeOptions {
+    CONFIG.lock().unwrap().borrow().transcribe.clone()
 }
 
-#[allow(dead_code)]
 pub fn conf_path() -> String {
     let conf = CONFIG.lock().unwrap();
     let conf = conf.borrow();
@@ -32,7 +48,6 @@ pub fn conf_path() -> String {
     conf.config_path.clone()
 }
 
-#[allow(dead_code)]
 pub fn db_path() -> String {
     let conf = CONFIG.lock().unwrap();
     let conf = conf.borrow();
@@ -101,7 +116,11 @@ impl Config {
             Ok(text) => match serde_json::from_str::<Config>(&text) {
                 Ok(c) => {
                     self.ui = c.ui;
-                    self.socks5 = c.socks5;
+                    self.proxy = c.proxy;
+                    self.download = c.download;
+                    self.transcribe = c.transcribe;
+                    self.model = c.model;
+                    self.batch = c.batch;
                     Ok(())
                 }
                 Err(e) => Err(e.into()),
@@ -1,3 +1,5 @@
+use crate::transcribe::options::TranscribeOptions;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
     #[serde(skip)]
@@ -11,7 +13,21 @@ pub struct Config {
 
     pub ui: UI,
 
-    pub socks5: Socks5,
+    // Older configurations call it "socks5", and only have its first three fields
+    #[serde(default, alias = "socks5")]
+    pub proxy: Proxy,
+
+    #[serde(default)]
+    pub download: Download,
+
+    #[serde(default)]
+    pub transcribe: TranscribeOptions,
+
+    #[serde(default)]
+    pub model: Model,
+
+    #[serde(default)]
+    pub batch: Batch,
 }
 
 impl Default for Config {
@@ -21,7 +37,11 @@ impl Default for Config {
             db_path: "".to_string(),
             cache_dir: "".to_string(),
             ui: UI::default(),
-            socks5: Socks5::default(),
+            proxy: Proxy::default(),
+            download: Download::default(),
+            transcribe: TranscribeOptions::default(),
+            model: Model::default(),
+            batch: Batch::default(),
         }
     }
 }
@@ -48,18 +68,89 @@ impl Default for UI {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Socks5 {
+#[serde(default)]
+pub struct Proxy {
     pub enabled: bool,
     pub url: String,
     pub port: u16,
+    // http, https, socks5 or socks5h
+    pub scheme: String,
+    pub username: String,
+    pub password: String,
+    // Comma separated hosts, domains and IP ranges that are not proxied
+    pub no_proxy: String,
 }
 
-impl Default for Socks5 {
+impl Default for Proxy {
     fn default() -> Self {
         Self {
             enabled: false,
             url: "127.0.0.1".to_string(),
             port: 1080,
+            scheme: "socks5".to_string(),
+            username: "".to_string(),
+            password: "".to_string(),
+            no_proxy: "localhost,127.0.0.1".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Download {
+    // Models downloaded at the same time, the others wait in the queue
+    pub concurrency: u32,
+    // Base URLs tried in order in place of https://huggingface.co
+    pub mirrors: Vec<String>,
+    pub connect_timeout_secs: u64,
+    // Longest wait for the next chunk of a download, 0 to wait forever
+    pub read_timeout_secs: u64,
+}
+
+impl Default for Download {
+    fn default() -> Self {
+        Self {
+            concurrency: 2,
+            mirrors: vec!["https://huggingface.co".to_string()],
+            connect_timeout_secs: 10,
+            read_timeout_secs: 60,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Model {
+    // Loaded models are unloaded, least recently used first, above this size
+    pub memory_budget_mb: u64,
+    pub preload: bool,
+    pub last_used: String,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Self {
+            memory_budget_mb: 4096,
+            preload: true,
+            last_used: "".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Batch {
+    // Jobs transcribed at the same time
+    pub concurrency: u32,
+    // Extension of the subtitle format the results are exported to
+    pub format: String,
+}
+
+impl Default for Batch {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            format: "srt".to_string(),
         }
     }
 }
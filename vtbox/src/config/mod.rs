@@ -1,4 +1,8 @@
 mod conf;
 mod data;
 
-pub use conf::{cache_dir, config, init, save, socks5, ui};
+pub use conf::{
+    batch, cache_dir, conf_path, config, db_path, download, init, model, proxy, save, transcribe,
+    ui,
+};
+pub use data::Config;
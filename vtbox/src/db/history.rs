@@ -0,0 +1,216 @@
+use super::pool;
+use anyhow::Result;
+
+/// One transcription job. `options` and `output` hold the `TranscribeOptions` and the
+/// `TranscriberOutput` as JSON, `text` starts as the transcribed text and may be edited.
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+pub struct History {
+    pub id: i64,
+    pub path: String,
+    pub file_hash: String,
+    pub model: String,
+    pub options: String,
+    pub duration_ms: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub output: String,
+    pub text: String,
+}
+
+// The list does not need the segments, and only the start of the text
+const SUMMARY_COLUMNS: &str = "history.id, history.path, file_hash, model, options, \
+    duration_ms, created_at, updated_at, '' AS output, substr(history.text, 1, 200) AS text";
+
+// Shorter terms can not be looked up in the trigram index
+const MIN_FTS_TERM_LEN: usize = 3;
+
+pub async fn new() -> Result<()> {
+    let pool = pool()?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL,
+            file_hash TEXT NOT NULL,
+            model TEXT NOT NULL,
+            options TEXT NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            output TEXT NOT NULL,
+            text TEXT NOT NULL)",
+    )
+    .execute(&pool)
+    .await?;
+
+    // The trigram tokenizer also finds words in Chinese text, which has no spaces
+    sqlx::query(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS history_fts USING fts5(
+            path, text, content='history', content_rowid='id', tokenize='trigram')",
+    )
+    .execute(&pool)
+    .await?;
+
+    for trigger in [
+        "CREATE TRIGGER IF NOT EXISTS history_ai AFTER INSERT ON history BEGIN
+            INSERT INTO history_fts(rowid, path, text) VALUES (new.id, new.path, new.text);
+        END",
+        "CREATE TRIGGER IF NOT EXISTS history_ad AFTER DELETE ON history BEGIN
+            INSERT INTO history_fts(history_fts, rowid, path, text)
+                VALUES ('delete', old.id, old.path, old.text);
+        END",
+        "CREATE TRIGGER IF NOT EXISTS history_au AFTER UPDATE ON history BEGIN
+            INSERT INTO history_fts(history_fts, rowid, path, text)
+                VALUES ('delete', old.id, old.path, old.text);
+            INSERT INTO history_fts(rowid, path, text) VALUES (new.id, new.path, new.text);
+        END",
+    ] {
+        sqlx::query(trigger).execute(&pool).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn insert(item: &History) -> Result<i64> {
+    let result = sqlx::query(
+        "INSERT INTO history (path, file_hash, model, options, duration_ms, created_at,
+            updated_at, output, text) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&item.path)
+    .bind(&item.file_hash)
+    .bind(&item.model)
+    .bind(&item.options)
+    .bind(item.duration_ms)
+    .bind(item.created_at)
+    .bind(item.updated_at)
+    .bind(&item.output)
+    .bind(&item.text)
+    .execute(&pool()?)
+    .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn update_text(id: i64, text: &str, updated_at: i64) -> Result<()> {
+    sqlx::query("UPDATE history SET text = ?, updated_at = ? WHERE id = ?")
+        .bind(text)
+        .bind(updated_at)
+        .bind(id)
+        .execute(&pool()?)
+        .await?;
+    Ok(())
+}
+
+pub async fn delete(id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM history WHERE id = ?")
+        .bind(id)
+        .execute(&pool()?)
+        .await?;
+    Ok(())
+}
+
+pub async fn select(id: i64) -> Result<Option<History>> {
+    Ok(sqlx::query_as("SELECT * FROM history WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&pool()?)
+        .await?)
+}
+
+/// Summaries of all the jobs, the latest first.
+pub async fn select_all() -> Result<Vec<History>> {
+    Ok(sqlx::query_as(&format!(
+        "SELECT {SUMMARY_COLUMNS} FROM history ORDER BY created_at DESC, id DESC"
+    ))
+    .fetch_all(&pool()?)
+    .await?)
+}
+
+/// Summaries of the jobs whose text or path contain all the words of `query`.
+pub async fn search(query: &str) -> Result<Vec<History>> {
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    if terms.is_empty() {
+        return select_all().await;
+    }
+
+    if terms
+        .iter()
+        .all(|term| term.chars().count() >= MIN_FTS_TERM_LEN)
+    {
+        // Quoted, so that the terms are never taken as FTS5 operators
+        let fts_query = terms
+            .iter()
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        return Ok(sqlx::query_as(&format!(
+            "SELECT {SUMMARY_COLUMNS} FROM history
+                JOIN history_fts ON history_fts.rowid = history.id
+                WHERE history_fts MATCH ? ORDER BY rank"
+        ))
+        .bind(fts_query)
+        .fetch_all(&pool()?)
+        .await?);
+    }
+
+    let conditions = vec!["(text LIKE ? ESCAPE '\\' OR path LIKE ? ESCAPE '\\')"; terms.len()];
+    let sql = format!(
+        "SELECT {SUMMARY_COLUMNS} FROM history WHERE {} ORDER BY created_at DESC, id DESC",
+        conditions.join(" AND ")
+    );
+
+    let mut query = sqlx::query_as(&sql);
+    for term in terms {
+        let pattern = format!(
+            "%{}%",
+            term.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        query = query.bind(pattern.clone()).bind(pattern);
+    }
+
+    Ok(query.fetch_all(&pool()?).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(path: &str, text: &str) -> History {
+        History {
+            path: path.to_string(),
+            model: "ggml-base.bin".to_string(),
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history() -> Result<()> {
+        crate::db::init_memory().await?;
+
+        let first = insert(&history("/a/interview.mp3", "hello world 你好世界")).await?;
+        let second = insert(&history("/b/meeting.wav", "see you tomorrow")).await?;
+        assert_eq!(select_all().await?.len(), 2);
+
+        let found = search("world").await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, first);
+
+        // Too short for the index
+        assert_eq!(search("世界").await?[0].id, first);
+        assert_eq!(search("meeting").await?[0].id, second);
+        assert!(search("100%").await?.is_empty());
+
+        update_text(second, "edited text", 10).await?;
+        assert!(search("tomorrow").await?.is_empty());
+        assert_eq!(search("edited").await?[0].id, second);
+        assert_eq!(select(second).await?.unwrap().updated_at, 10);
+
+        delete(first).await?;
+        assert!(search("world").await?.is_empty());
+        assert!(select(first).await?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::{anyhow, Result};
+use sqlx::{
+    sqlite::{Sqlite, SqliteConnectOptions, SqlitePoolOptions},
+    Pool,
+};
+use std::sync::Mutex;
+
+pub mod history;
+
+const MAX_CONNECTIONS: u32 = 3;
+
+lazy_static! {
+    static ref POOL: Mutex<Option<Pool<Sqlite>>> = Mutex::new(None);
+}
+
+fn pool() -> Result<Pool<Sqlite>> {
+    POOL.lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| anyhow!("database is not initialized"))
+}
+
+pub async fn init(db_path: &str) -> Result<()> {
+    let options = SqliteConnectOptions::new()
+        .filename(db_path)
+        .create_if_missing(true);
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(MAX_CONNECTIONS)
+        .connect_with(options)
+        .await?;
+    *POOL.lock().unwrap() = Some(pool);
+
+    history::new().await
+}
+
+#[cfg(test)]
+pub(crate) async fn init_memory() -> Result<()> {
+    // All the connections must share the one in-memory database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    *POOL.lock().unwrap() = Some(pool);
+
+    history::new().await
+}
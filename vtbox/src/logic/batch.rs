@@ -0,0 +1,474 @@
+use super::history;
+use crate::slint_generatedAppWindow::{AppWindow, BatchJobItem, Logic, Store};
+use crate::util::translator::tr;
+use crate::{
+    config,
+    message::{async_message_success, async_message_warn},
+    model_cache,
+    transcribe::{
+        audio_parser,
+        export::{self, SubtitleFormat},
+        job::{AbortHandle, Aborted, JobControl},
+        options::TranscribeOptions,
+        transcriber::Transcriber,
+    },
+    util,
+};
+use crate::{message_info, message_warn};
+use anyhow::Result;
+use native_dialog::FileDialog;
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel, Weak};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::task::spawn;
+use uuid::Uuid;
+
+static IS_RUNNING: AtomicBool = AtomicBool::new(false);
+static IS_STOPPING: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    // The queue, in the order the jobs are run
+    static ref JOBS: Mutex<Vec<Job>> = Mutex::new(vec![]);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn text(&self) -> String {
+        match self {
+            JobStatus::Queued => tr("排队中"),
+            JobStatus::Running => tr("转换中"),
+            JobStatus::Done => tr("已完成"),
+            JobStatus::Failed => tr("失败"),
+            JobStatus::Cancelled => tr("已取消"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Job {
+    uuid: String,
+    path: String,
+    status: JobStatus,
+    progress: f32,
+    // The exported file, or why the job failed
+    result: String,
+    abort: Option<AbortHandle>,
+}
+
+impl Job {
+    fn new(path: &str) -> Self {
+        Self {
+            uuid: Uuid::new_v4().to_string(),
+            path: path.to_string(),
+            status: JobStatus::Queued,
+            progress: 0.0,
+            result: String::default(),
+            abort: None,
+        }
+    }
+
+    fn item(&self) -> BatchJobItem {
+        BatchJobItem {
+            uuid: self.uuid.as_str().into(),
+            name: Path::new(&self.path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+                .into(),
+            status: self.status.text().into(),
+            progress: self.progress,
+            result: self.result.as_str().into(),
+        }
+    }
+}
+
+pub fn init(ui: &AppWindow) {
+    let conf = config::batch();
+    let formats: Vec<SharedString> = SubtitleFormat::all()
+        .iter()
+        .map(|f| f.extension().into())
+        .collect();
+    let format_index = SubtitleFormat::all()
+        .iter()
+        .position(|f| f.extension() == conf.format)
+        .unwrap_or_default();
+
+    ui.global::<Store>()
+        .set_batch_formats(ModelRc::new(VecModel::from(formats)));
+    ui.global::<Store>()
+        .set_batch_format_index(format_index as i32);
+    ui.global::<Store>()
+        .set_batch_concurrency(conf.concurrency.max(1) as i32);
+    ui.global::<Store>()
+        .set_batch_jobs(ModelRc::new(VecModel::<BatchJobItem>::default()));
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_add_batch_files(move || {
+        let ui = ui_handle.unwrap();
+        let extensions = audio_parser::supported_extensions();
+
+        match FileDialog::new()
+            .set_location("~")
+            .add_filter("Audio/Video", &extensions)
+            .show_open_multiple_file()
+        {
+            Ok(files) => add_jobs(&ui, files),
+            Err(e) => {
+                message_warn!(
+                    &ui,
+                    format!("{}. {}: {e:?}", tr("打开文件失败"), tr("原因"))
+                );
+            }
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_add_batch_folder(move || {
+        let ui = ui_handle.unwrap();
+
+        match FileDialog::new().set_location("~").show_open_single_dir() {
+            Ok(Some(dir)) => match audio_files(&dir) {
+                Ok(files) => add_jobs(&ui, files),
+                Err(e) => {
+                    message_warn!(
+                        &ui,
+                        format!("{}. {}: {e:?}", tr("打开文件失败"), tr("原因"))
+                    );
+                }
+            },
+            Err(e) => {
+                message_warn!(
+                    &ui,
+                    format!("{}. {}: {e:?}", tr("打开文件失败"), tr("原因"))
+                );
+            }
+            _ => (),
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_remove_batch_job(move |uuid| {
+        let ui = ui_handle.unwrap();
+
+        let mut jobs = JOBS.lock().unwrap();
+        if let Some(job) = jobs.iter().find(|job| job.uuid == uuid.as_str()) {
+            if let Some(abort) = &job.abort {
+                abort.abort();
+            }
+        }
+        jobs.retain(|job| job.uuid != uuid.as_str());
+        load_jobs(&ui, &jobs);
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_retry_batch_job(move |uuid| {
+        let ui = ui_handle.unwrap();
+
+        let mut jobs = JOBS.lock().unwrap();
+        if let Some(job) = jobs.iter_mut().find(|job| job.uuid == uuid.as_str()) {
+            if job.status != JobStatus::Running {
+                *job = Job {
+                    uuid: job.uuid.clone(),
+                    ..Job::new(&job.path)
+                };
+                set_job_item(&ui, job);
+            }
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_move_batch_job(move |uuid, offset| {
+        let ui = ui_handle.unwrap();
+
+        let mut jobs = JOBS.lock().unwrap();
+        if let Some(index) = jobs.iter().position(|job| job.uuid == uuid.as_str()) {
+            let to = (index as i32 + offset).clamp(0, jobs.len() as i32 - 1) as usize;
+            let job = jobs.remove(index);
+            jobs.insert(to, job);
+            load_jobs(&ui, &jobs);
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_clear_batch_jobs(move || {
+        let ui = ui_handle.unwrap();
+
+        // Running jobs stay in the list
+        let mut jobs = JOBS.lock().unwrap();
+        jobs.retain(|job| job.status == JobStatus::Running);
+        load_jobs(&ui, &jobs);
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_start_batch(
+        move |model_name, language, translate, format_index, concurrency| {
+            let ui = ui_handle.unwrap();
+
+            if IS_RUNNING.load(Ordering::SeqCst) {
+                message_warn!(ui, tr("有任务正在运行，稍后重试"));
+                return;
+            }
+
+            if model_name.is_empty() {
+                message_info!(ui, tr("请先下载模型"));
+                return;
+            }
+
+            if !JOBS
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|job| job.status == JobStatus::Queued)
+            {
+                message_info!(ui, tr("没有排队的任务"));
+                return;
+            }
+
+            let format = SubtitleFormat::all()
+                .get(format_index as usize)
+                .copied()
+                .unwrap_or(SubtitleFormat::Srt);
+            let concurrency = concurrency.max(1) as u32;
+
+            let mut options = config::transcribe();
+            options.language = super::v2t::parse_language(&language);
+            options.translate = translate;
+
+            let mut conf = config::config();
+            conf.transcribe = options.clone();
+            conf.batch.format = format.extension().to_string();
+            conf.batch.concurrency = concurrency;
+            conf.model.last_used = model_name.to_string();
+            if let Err(e) = config::save(conf) {
+                log::warn!("{e:?}");
+            }
+
+            IS_RUNNING.store(true, Ordering::SeqCst);
+            IS_STOPPING.store(false, Ordering::SeqCst);
+            ui.global::<Store>().set_batch_running(true);
+            message_info!(ui, tr("正在转换..."));
+
+            let (ui, model_name) = (ui.as_weak(), model_name.to_string());
+            spawn(async move {
+                match run_batch(ui.clone(), &model_name, options, format, concurrency).await {
+                    Err(e) => async_message_warn(
+                        ui.clone(),
+                        format!("{}. {}: {e:?}", tr("转换失败"), tr("原因")),
+                    ),
+                    _ => async_message_success(ui.clone(), tr("批量转换完成")),
+                }
+
+                IS_RUNNING.store(false, Ordering::SeqCst);
+                let _ = slint::invoke_from_event_loop(move || {
+                    ui.unwrap().global::<Store>().set_batch_running(false);
+                });
+            });
+        },
+    );
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_stop_batch(move || {
+        let ui = ui_handle.unwrap();
+
+        if !IS_RUNNING.load(Ordering::SeqCst) || IS_STOPPING.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        for job in JOBS.lock().unwrap().iter() {
+            if let Some(abort) = &job.abort {
+                abort.abort();
+            }
+        }
+        message_info!(ui, tr("正在取消..."));
+    });
+}
+
+fn add_jobs(ui: &AppWindow, files: Vec<PathBuf>) {
+    let mut jobs = JOBS.lock().unwrap();
+    for file in files {
+        let path = file.to_string_lossy();
+        if !jobs.iter().any(|job| job.path == path) {
+            jobs.push(Job::new(&path));
+        }
+    }
+    load_jobs(ui, &jobs);
+}
+
+// The audio files of `dir`, sorted by name
+fn audio_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let extensions = audio_parser::supported_extensions();
+
+    let mut files: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let extension = path.extension()?.to_str()?.to_lowercase();
+            if path.is_file() && extensions.contains(&extension.as_str()) {
+                Some(path)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    files.sort();
+    Ok(files)
+}
+
+fn load_jobs(ui: &AppWindow, jobs: &[Job]) {
+    ui.global::<Store>()
+        .get_batch_jobs()
+        .as_any()
+        .downcast_ref::<VecModel<BatchJobItem>>()
+        .expect("We know we set a VecModel earlier")
+        .set_vec(jobs.iter().map(|job| job.item()).collect::<Vec<_>>());
+}
+
+fn set_job_item(ui: &AppWindow, job: &Job) {
+    let items = ui.global::<Store>().get_batch_jobs();
+    if let Some(index) = items.iter().position(|item| item.uuid == job.uuid) {
+        items.set_row_data(index, job.item());
+    }
+}
+
+// Applies `f` to the job and shows the change
+fn update_job(ui: &Weak<AppWindow>, uuid: &str, f: impl FnOnce(&mut Job)) {
+    let mut jobs = JOBS.lock().unwrap();
+    if let Some(job) = jobs.iter_mut().find(|job| job.uuid == uuid) {
+        f(job);
+
+        let (ui, job) = (ui.clone(), job.clone());
+        let _ = slint::invoke_from_event_loop(move || {
+            set_job_item(&ui.unwrap(), &job);
+        });
+    }
+}
+
+// Marks the first queued job as running
+fn next_job(jobs: &mut [Job]) -> Option<(String, String, AbortHandle)> {
+    let job = jobs
+        .iter_mut()
+        .find(|job| job.status == JobStatus::Queued)?;
+
+    let abort = AbortHandle::new();
+    job.status = JobStatus::Running;
+    job.abort = Some(abort.clone());
+    Some((job.uuid.clone(), job.path.clone(), abort))
+}
+
+async fn run_batch(
+    ui: Weak<AppWindow>,
+    model_name: &str,
+    options: TranscribeOptions,
+    format: SubtitleFormat,
+    concurrency: u32,
+) -> Result<()> {
+    // Load the model once for all the workers
+    let path = model_cache::model_path(model_name);
+    let trans = tokio::task::spawn_blocking(move || model_cache::transcriber(&path)).await??;
+
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| {
+            let (ui, trans, options) = (ui.clone(), trans.clone(), options.clone());
+            let model_name = model_name.to_string();
+            spawn(async move { run_worker(ui, trans, model_name, options, format).await })
+        })
+        .collect();
+
+    for worker in workers {
+        let _ = worker.await;
+    }
+
+    Ok(())
+}
+
+async fn run_worker(
+    ui: Weak<AppWindow>,
+    trans: Arc<Transcriber>,
+    model_name: String,
+    options: TranscribeOptions,
+    format: SubtitleFormat,
+) {
+    while !IS_STOPPING.load(Ordering::SeqCst) {
+        let (uuid, path, abort) = match next_job(&mut JOBS.lock().unwrap()) {
+            Some(v) => v,
+            None => return,
+        };
+        update_job(&ui, &uuid, |_| ());
+
+        let (ui_progress, uuid_progress) = (ui.clone(), uuid.clone());
+        let control = JobControl::new()
+            .with_abort_handle(abort)
+            .on_progress(move |progress| {
+                if let Some(percent) = progress.percent {
+                    update_job(&ui_progress, &uuid_progress, |job| {
+                        job.progress = percent / 100.0
+                    });
+                }
+            });
+
+        let result = trans
+            .clone()
+            .transcribe_async(path.as_str(), options.clone(), control)
+            .await
+            .and_then(|output| {
+                // Next to the source, under another name when a file is already there
+                let output_path = Path::new(&path).with_extension(format.extension());
+                let output_path = util::fs::create_new_file(&output_path)?;
+                if let Err(e) = export::save(&output, format, &output_path) {
+                    let _ = fs::remove_file(&output_path);
+                    return Err(e);
+                }
+
+                history::record(ui.clone(), &path, &model_name, &options, &output);
+                Ok(output_path)
+            });
+
+        update_job(&ui, &uuid, |job| {
+            job.abort = None;
+            match result {
+                Ok(output_path) => {
+                    job.status = JobStatus::Done;
+                    job.progress = 1.0;
+                    job.result = output_path.to_string_lossy().to_string();
+                }
+                Err(e) if e.is::<Aborted>() => {
+                    job.status = JobStatus::Cancelled;
+                    job.result = String::default();
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.result = format!("{e:?}");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_job() {
+        let mut jobs = vec![Job::new("a.mp3"), Job::new("b.mp3")];
+
+        let (_, path, _) = next_job(&mut jobs).unwrap();
+        assert_eq!(path, "a.mp3");
+        let (_, path, _) = next_job(&mut jobs).unwrap();
+        assert_eq!(path, "b.mp3");
+        assert!(next_job(&mut jobs).is_none());
+
+        assert!(jobs.iter().all(|job| job.status == JobStatus::Running));
+        assert!(jobs.iter().all(|job| job.abort.is_some()));
+    }
+}
@@ -0,0 +1,173 @@
+use super::model::{self, get_model_data, init_model};
+use crate::slint_generatedAppWindow::{AppWindow, BundleModel, ExportBundleDialog, Logic, Store};
+use crate::util::translator::tr;
+use crate::{message_info, message_success, message_warn};
+use native_dialog::FileDialog;
+use slint::{ComponentHandle, Model, ModelRc, VecModel};
+use std::path::PathBuf;
+use tokio::task::spawn;
+
+const BUNDLE_FILENAME: &str = "vtbox-models.tar";
+
+pub fn init(ui: &AppWindow) {
+    ui.global::<Store>()
+        .set_bundle_models(ModelRc::new(VecModel::default()));
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_show_export_bundle(move |type_index, uuid| {
+            let ui = ui_handle.unwrap();
+            let names = match model::installed_models(type_index) {
+                Ok(names) if !names.is_empty() => names,
+                Ok(_) => {
+                    message_info!(&ui, tr("没有可导出的模型"));
+                    return;
+                }
+                Err(e) => {
+                    message_warn!(&ui, format!("{}. {}: {e:?}", tr("导出失败"), tr("原因")));
+                    return;
+                }
+            };
+
+            // The selected model is checked to begin with
+            let selected = get_model_data(&ui, &uuid).map(|item| item.name);
+            let models = names
+                .into_iter()
+                .map(|name| BundleModel {
+                    checked: selected.as_ref().is_some_and(|s| *s == name),
+                    name: name.into(),
+                })
+                .collect::<Vec<_>>();
+
+            let store = ui.global::<Store>();
+            store
+                .get_bundle_models()
+                .as_any()
+                .downcast_ref::<VecModel<BundleModel>>()
+                .expect("We know we set a VecModel earlier")
+                .set_vec(models);
+            store.set_export_bundle_dialog(ExportBundleDialog {
+                show: true,
+                type_index,
+            });
+        });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_export_bundle(move |type_index| {
+        let ui = ui_handle.unwrap();
+        let names = ui
+            .global::<Store>()
+            .get_bundle_models()
+            .iter()
+            .filter(|model| model.checked)
+            .map(|model| model.name.to_string())
+            .collect::<Vec<_>>();
+
+        if names.is_empty() {
+            message_info!(&ui, tr("请先选择一个模型"));
+            return;
+        }
+
+        let archive = match FileDialog::new()
+            .set_location("~")
+            .set_filename(BUNDLE_FILENAME)
+            .add_filter("Tar", &["tar"])
+            .show_save_single_file()
+        {
+            Ok(Some(archive)) => archive,
+            Ok(None) => return,
+            Err(e) => {
+                message_warn!(&ui, format!("{}. {}: {e:?}", tr("导出失败"), tr("原因")));
+                return;
+            }
+        };
+
+        message_info!(&ui, tr("正在导出..."));
+        let ui = ui.as_weak();
+        spawn(async move {
+            let result = match tokio::task::spawn_blocking(move || {
+                model::export_bundle(type_index, &names, &archive)
+            })
+            .await
+            {
+                Ok(result) => result,
+                Err(e) => Err(e.into()),
+            };
+
+            let _ = slint::invoke_from_event_loop(move || {
+                let ui = ui.unwrap();
+                match result {
+                    Ok(_) => {
+                        message_success!(&ui, tr("导出成功"));
+                    }
+                    Err(e) => {
+                        message_warn!(&ui, format!("{}. {}: {e:?}", tr("导出失败"), tr("原因")));
+                    }
+                }
+            });
+        });
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_import_bundle(move || {
+        let ui = ui_handle.unwrap();
+
+        let archive: PathBuf = match FileDialog::new()
+            .set_location("~")
+            .add_filter("Tar", &["tar"])
+            .show_open_single_file()
+        {
+            Ok(Some(archive)) => archive,
+            Ok(None) => return,
+            Err(e) => {
+                message_warn!(&ui, format!("{}. {}: {e:?}", tr("导入失败"), tr("原因")));
+                return;
+            }
+        };
+
+        message_info!(&ui, tr("正在导入..."));
+        let ui = ui.as_weak();
+        spawn(async move {
+            let result =
+                match tokio::task::spawn_blocking(move || model::import_bundle(&archive)).await {
+                    Ok(result) => result,
+                    Err(e) => Err(e.into()),
+                };
+
+            let _ = slint::invoke_from_event_loop(move || {
+                let ui = ui.unwrap();
+                let (type_index, imported) = match result {
+                    Ok(result) => result,
+                    Err(e) => {
+                        message_warn!(&ui, format!("{}. {}: {e:?}", tr("导入失败"), tr("原因")));
+                        return;
+                    }
+                };
+
+                if type_index == ui.get_model_type_index() {
+                    init_model(&ui, type_index);
+                }
+
+                let renamed = imported
+                    .iter()
+                    .filter(|model| model.name != model.installed_as)
+                    .map(|model| format!("{} -> {}", model.name, model.installed_as))
+                    .collect::<Vec<_>>();
+
+                if renamed.is_empty() {
+                    message_success!(&ui, tr("导入成功"));
+                } else {
+                    message_success!(
+                        &ui,
+                        format!(
+                            "{}. {}: {}",
+                            tr("导入成功"),
+                            tr("重命名"),
+                            renamed.join(", ")
+                        )
+                    );
+                }
+            });
+        });
+    });
+}
@@ -0,0 +1,522 @@
+use super::model::{self, download_model, get_model_data, init_model, model_relative_path};
+use crate::slint_generatedAppWindow::{AppWindow, Logic, ModelItem, Store};
+use crate::util::translator::tr;
+use crate::{config, message::async_message_warn, util};
+use crate::{message_info, message_success, message_warn};
+use slint::{ComponentHandle, Model, Weak};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::task::{spawn, JoinHandle};
+use transcribe::model_handler::{self, DownloadProgress};
+
+// The rows are not redrawn, and the speed is not measured, more often than this
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+lazy_static! {
+    // The downloads of this session, in the order they were asked for
+    static ref DOWNLOADS: Mutex<Vec<Download>> = Mutex::new(vec![]);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DownloadStatus {
+    Queued,
+    Running,
+    Paused,
+    Failed,
+    Done,
+    // Its partial file is being removed, a new download of the model waits for it
+    Cancelling,
+}
+
+impl DownloadStatus {
+    // The raw status of the model rows, which also has "Downloaded" and "Undownload"
+    fn text(&self) -> &'static str {
+        match self {
+            DownloadStatus::Queued => "Queued",
+            DownloadStatus::Running => "Downloading",
+            DownloadStatus::Paused => "Paused",
+            DownloadStatus::Failed => "Failed",
+            DownloadStatus::Done => "Downloaded",
+            DownloadStatus::Cancelling => "Cancelling",
+        }
+    }
+
+    // Shown in the model row instead of the status of the model file
+    fn is_shown(&self) -> bool {
+        !matches!(self, DownloadStatus::Done | DownloadStatus::Cancelling)
+    }
+
+    // Holds the model file, so that it can not be downloaded a second time
+    fn is_active(&self) -> bool {
+        matches!(
+            self,
+            DownloadStatus::Queued | DownloadStatus::Running | DownloadStatus::Paused
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DownloadState {
+    status: DownloadStatus,
+    progress: DownloadProgress,
+    // Bytes per second, smoothed over the last measures
+    speed: f64,
+}
+
+impl DownloadState {
+    fn eta(&self) -> Option<Duration> {
+        let total = self.progress.total?;
+        if self.speed < 1.0 {
+            return None;
+        }
+
+        let remaining = total.saturating_sub(self.progress.downloaded);
+        Some(Duration::from_secs_f64(remaining as f64 / self.speed))
+    }
+
+    fn show(&self, item: &mut ModelItem) {
+        item.status = self.status.text().into();
+        item.progress = self.progress.fraction().unwrap_or_default();
+
+        if self.progress.downloaded > 0 {
+            item.size = match self.progress.total {
+                Some(total) => format!(
+                    "{}/{}",
+                    util::fs::pretty_size(self.progress.downloaded),
+                    util::fs::pretty_size(total)
+                ),
+                None => util::fs::pretty_size(self.progress.downloaded),
+            }
+            .into();
+        }
+
+        item.detail = match (self.status, self.eta()) {
+            (DownloadStatus::Running, Some(eta)) => format!(
+                "{}/s {}",
+                util::fs::pretty_size(self.speed as u64),
+                util::time::from_timestamp(eta.as_secs() as i64, "%H:%M:%S").unwrap_or_default()
+            ),
+            (DownloadStatus::Running, None) if self.speed >= 1.0 => {
+                format!("{}/s", util::fs::pretty_size(self.speed as u64))
+            }
+            _ => String::default(),
+        }
+        .into();
+    }
+}
+
+#[derive(Debug)]
+struct Download {
+    type_index: i32,
+    name: String,
+    state: DownloadState,
+    // The running task, or the aborted one or the cleanup that must end before the file is
+    // touched again
+    task: Option<JoinHandle<()>>,
+}
+
+impl Download {
+    fn new(type_index: i32, name: &str) -> Self {
+        Self {
+            type_index,
+            name: name.to_string(),
+            state: DownloadState {
+                status: DownloadStatus::Queued,
+                progress: DownloadProgress::default(),
+                speed: 0.0,
+            },
+            task: None,
+        }
+    }
+
+    fn is(&self, type_index: i32, name: &str) -> bool {
+        self.type_index == type_index && self.name == name
+    }
+}
+
+pub fn init(ui: &AppWindow) {
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_download_model(move |type_index, uuid| {
+            let ui = ui_handle.unwrap();
+            let name = match model_name(&ui, &uuid) {
+                Some(name) => name,
+                None => return,
+            };
+
+            if !model::is_in_catalog(type_index, &name) {
+                message_info!(&ui, tr("不支持下载"));
+                return;
+            }
+
+            if enqueue(type_index, &name) {
+                message_info!(&ui, tr("已加入下载队列"));
+                schedule(&ui.as_weak());
+            } else {
+                message_info!(&ui, tr("正在下载..."));
+            }
+        });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_pause_download(move |type_index, uuid| {
+            let ui = ui_handle.unwrap();
+            let name = match model_name(&ui, &uuid) {
+                Some(name) => name,
+                None => return,
+            };
+
+            let paused = update(&ui.as_weak(), type_index, &name, |download| {
+                match download.state.status {
+                    DownloadStatus::Running => {
+                        if let Some(task) = &download.task {
+                            task.abort();
+                        }
+                    }
+                    DownloadStatus::Queued => (),
+                    _ => return false,
+                }
+
+                download.state.status = DownloadStatus::Paused;
+                download.state.speed = 0.0;
+                true
+            });
+
+            if paused == Some(true) {
+                schedule(&ui.as_weak());
+            } else {
+                message_info!(&ui, tr("没有正在进行的下载"));
+            }
+        });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_resume_download(move |type_index, uuid| {
+            let ui = ui_handle.unwrap();
+            let name = match model_name(&ui, &uuid) {
+                Some(name) => name,
+                None => return,
+            };
+
+            let resumed = update(&ui.as_weak(), type_index, &name, |download| match download
+                .state
+                .status
+            {
+                DownloadStatus::Paused | DownloadStatus::Failed => {
+                    download.state.status = DownloadStatus::Queued;
+                    true
+                }
+                _ => false,
+            });
+
+            if resumed == Some(true) {
+                schedule(&ui.as_weak());
+            } else {
+                message_info!(&ui, tr("没有暂停的下载"));
+            }
+        });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_cancel_download(move |type_index, uuid| {
+            let ui = ui_handle.unwrap();
+            let name = match model_name(&ui, &uuid) {
+                Some(name) => name,
+                None => return,
+            };
+
+            // The download is kept until its partial file is gone, so that downloading the
+            // model again waits for the cleanup
+            {
+                let mut downloads = DOWNLOADS.lock().unwrap();
+                let download = match downloads
+                    .iter_mut()
+                    .find(|d| d.is(type_index, &name) && d.state.status.is_shown())
+                {
+                    Some(download) => download,
+                    None => {
+                        message_info!(&ui, tr("没有正在进行的下载"));
+                        return;
+                    }
+                };
+
+                // Only a running task is still downloading, the others were aborted already
+                let task = download.task.take();
+                if let (Some(task), DownloadStatus::Running) = (&task, download.state.status) {
+                    task.abort();
+                }
+
+                download.state.status = DownloadStatus::Cancelling;
+                download.state.progress = DownloadProgress::default();
+                download.state.speed = 0.0;
+                download.task = Some(spawn(clean_up(type_index, name.clone(), task)));
+            }
+
+            init_model(&ui, type_index);
+            schedule(&ui.as_weak());
+            message_success!(&ui, tr("已取消下载"));
+        });
+}
+
+/// Shows the state of the model's download, if it has one, in its row.
+pub fn show_download(type_index: i32, item: &mut ModelItem) {
+    let downloads = DOWNLOADS.lock().unwrap();
+    if let Some(download) = downloads.iter().find(|d| d.is(type_index, &item.name)) {
+        if download.state.status.is_shown() {
+            download.state.show(item);
+        }
+    }
+}
+
+/// Whether the model file is held by a download that is not over.
+pub fn is_downloading(type_index: i32, name: &str) -> bool {
+    DOWNLOADS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|d| d.is(type_index, name) && d.state.status.is_active())
+}
+
+fn model_name(ui: &AppWindow, uuid: &str) -> Option<String> {
+    if uuid.is_empty() {
+        return None;
+    }
+
+    match get_model_data(ui, uuid) {
+        Some(item) => Some(item.name.to_string()),
+        None => {
+            message_warn!(ui, tr("内部错误，请刷新列表"));
+            None
+        }
+    }
+}
+
+fn model_file(type_index: i32, name: &str) -> std::path::PathBuf {
+    Path::new(&config::cache_dir())
+        .join(model_relative_path(type_index))
+        .join(name)
+}
+
+// Queues the model, unless it is already being downloaded
+fn enqueue(type_index: i32, name: &str) -> bool {
+    let mut downloads = DOWNLOADS.lock().unwrap();
+    match downloads.iter_mut().find(|d| d.is(type_index, name)) {
+        Some(download) if download.state.status.is_active() => false,
+        Some(download) => {
+            download.state.status = DownloadStatus::Queued;
+            download.state.speed = 0.0;
+            true
+        }
+        None => {
+            downloads.push(Download::new(type_index, name));
+            true
+        }
+    }
+}
+
+// Marks the first queued downloads as running, up to `concurrency` running ones
+fn claim_queued(downloads: &mut [Download], concurrency: usize) -> Vec<usize> {
+    let running = downloads
+        .iter()
+        .filter(|d| d.state.status == DownloadStatus::Running)
+        .count();
+
+    let claimed: Vec<usize> = downloads
+        .iter()
+        .enumerate()
+        .filter(|(_, d)| d.state.status == DownloadStatus::Queued)
+        .map(|(index, _)| index)
+        .take(concurrency.saturating_sub(running))
+        .collect();
+
+    for index in claimed.iter() {
+        downloads[*index].state.status = DownloadStatus::Running;
+    }
+    claimed
+}
+
+// Starts the queued downloads there is room for
+fn schedule(ui: &Weak<AppWindow>) {
+    let concurrency = config::download().concurrency.max(1) as usize;
+    let mut downloads = DOWNLOADS.lock().unwrap();
+
+    for index in claim_queued(&mut downloads, concurrency) {
+        let download = &mut downloads[index];
+        let previous = download.task.take();
+        download.task = Some(spawn(run(
+            ui.clone(),
+            download.type_index,
+            download.name.clone(),
+            previous,
+        )));
+        show_state(ui, download);
+    }
+}
+
+async fn run(ui: Weak<AppWindow>, type_index: i32, name: String, previous: Option<JoinHandle<()>>) {
+    // A paused task may still be finishing a write to the partial file
+    if let Some(previous) = previous {
+        let _ = previous.await;
+    }
+
+    let mut last_measure: Option<(Instant, u64)> = None;
+    let (ui_progress, name_progress) = (ui.clone(), name.clone());
+    let result = download_model(type_index, &name, move |progress| {
+        let (measured_at, measured) = match last_measure {
+            Some(measure) => measure,
+            None => {
+                last_measure = Some((Instant::now(), progress.downloaded));
+                return;
+            }
+        };
+
+        let elapsed = measured_at.elapsed();
+        if elapsed < PROGRESS_INTERVAL {
+            return;
+        }
+        last_measure = Some((Instant::now(), progress.downloaded));
+
+        let speed = progress.downloaded.saturating_sub(measured) as f64 / elapsed.as_secs_f64();
+        update(&ui_progress, type_index, &name_progress, |download| {
+            download.state.progress = progress;
+            download.state.speed = match download.state.speed {
+                speed_before if speed_before > 0.0 => speed_before * 0.7 + speed * 0.3,
+                _ => speed,
+            };
+        });
+    })
+    .await;
+
+    let done = result.is_ok();
+    update(&ui, type_index, &name, |download| {
+        download.task = None;
+        download.state.speed = 0.0;
+        download.state.status = match done {
+            true => DownloadStatus::Done,
+            false => DownloadStatus::Failed,
+        };
+    });
+
+    match result {
+        Err(e) => async_message_warn(
+            ui.clone(),
+            format!("{}. {}: {e:?}", tr("下载失败"), tr("原因")),
+        ),
+        _ => {
+            let ui = ui.clone();
+            let _ = slint::invoke_from_event_loop(move || {
+                let ui = ui.unwrap();
+                if type_index == ui.get_model_type_index() {
+                    init_model(&ui, type_index);
+                }
+                message_success!(ui, tr("下载成功"));
+            });
+        }
+    }
+
+    schedule(&ui);
+}
+
+// Removes the partial file of a cancelled download once its task can no longer write to it
+async fn clean_up(type_index: i32, name: String, task: Option<JoinHandle<()>>) {
+    if let Some(task) = task {
+        let _ = task.await;
+    }
+
+    let path = model_file(type_index, &name);
+    match tokio::task::spawn_blocking(move || model_handler::remove_part(&path)).await {
+        Ok(Err(e)) => log::warn!("{e:?}"),
+        Err(e) => log::warn!("{e:?}"),
+        _ => (),
+    }
+
+    // Unless it was downloaded again meanwhile
+    DOWNLOADS
+        .lock()
+        .unwrap()
+        .retain(|d| !d.is(type_index, &name) || d.state.status != DownloadStatus::Cancelling);
+}
+
+// Applies `f` to the download and shows the change, `None` when there is no download
+fn update<T>(
+    ui: &Weak<AppWindow>,
+    type_index: i32,
+    name: &str,
+    f: impl FnOnce(&mut Download) -> T,
+) -> Option<T> {
+    let mut downloads = DOWNLOADS.lock().unwrap();
+    let download = downloads.iter_mut().find(|d| d.is(type_index, name))?;
+
+    let result = f(download);
+    show_state(ui, download);
+    Some(result)
+}
+
+fn show_state(ui: &Weak<AppWindow>, download: &Download) {
+    let (ui, type_index, name, state) = (
+        ui.clone(),
+        download.type_index,
+        download.name.clone(),
+        download.state,
+    );
+
+    let _ = slint::invoke_from_event_loop(move || {
+        let ui = ui.unwrap();
+        if type_index != ui.get_model_type_index() || !state.status.is_shown() {
+            return;
+        }
+
+        let items = ui.global::<Store>().get_model_datas();
+        if let Some(index) = items.iter().position(|item| item.name == name) {
+            let mut item = items.row_data(index).unwrap();
+            state.show(&mut item);
+            items.set_row_data(index, item);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_queued() {
+        let mut downloads: Vec<Download> = ["a.bin", "b.bin", "c.bin", "d.bin"]
+            .iter()
+            .map(|name| Download::new(0, name))
+            .collect();
+        downloads[0].state.status = DownloadStatus::Running;
+        downloads[1].state.status = DownloadStatus::Paused;
+
+        assert_eq!(claim_queued(&mut downloads, 2), vec![2]);
+        assert_eq!(downloads[2].state.status, DownloadStatus::Running);
+        assert_eq!(downloads[3].state.status, DownloadStatus::Queued);
+        assert!(claim_queued(&mut downloads, 2).is_empty());
+
+        downloads[0].state.status = DownloadStatus::Done;
+        assert_eq!(claim_queued(&mut downloads, 2), vec![3]);
+    }
+
+    #[test]
+    fn test_download_state() {
+        let mut state = DownloadState {
+            status: DownloadStatus::Running,
+            progress: DownloadProgress {
+                downloaded: 1024 * 1024,
+                total: Some(11 * 1024 * 1024),
+            },
+            speed: 0.0,
+        };
+        assert_eq!(state.eta(), None);
+
+        state.speed = 1024.0 * 1024.0;
+        assert_eq!(state.eta(), Some(Duration::from_secs(10)));
+
+        let mut item = ModelItem::default();
+        state.show(&mut item);
+        assert_eq!(item.status, "Downloading");
+        assert_eq!(item.size, "1M/11M");
+        assert_eq!(item.detail, "1M/s 00:00:10");
+    }
+}
@@ -0,0 +1,292 @@
+use super::v2t;
+use crate::db::{self, history::History};
+use crate::message_warn;
+use crate::slint_generatedAppWindow::{AppWindow, HistoryItem, Logic, Store};
+use crate::util::translator::tr;
+use crate::{
+    message::{async_message_success, async_message_warn},
+    transcribe::{audio_parser, options::TranscribeOptions, transcriber::TranscriberOutput},
+    util,
+};
+use anyhow::Result;
+use chrono::Local;
+use slint::{ComponentHandle, Model, ModelRc, VecModel, Weak};
+use tokio::task::spawn;
+
+pub fn init(ui: &AppWindow) {
+    ui.global::<Store>()
+        .set_history_items(ModelRc::new(VecModel::default()));
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_search_history(move |query| {
+        let (ui, query) = (ui_handle.clone(), query.to_string());
+        spawn(async move {
+            match db::history::search(&query).await {
+                Ok(items) => {
+                    let _ = slint::invoke_from_event_loop(move || {
+                        set_items(&ui.unwrap(), items);
+                    });
+                }
+                Err(e) => {
+                    async_message_warn(ui, format!("{}. {}: {e:?}", tr("加载失败"), tr("原因")))
+                }
+            }
+        });
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_select_history(move |id| {
+        let ui = ui_handle.unwrap();
+        let id = match parse_id(&ui, &id) {
+            Some(id) => id,
+            None => return,
+        };
+
+        with_history(ui.as_weak(), id, |ui, item, _| {
+            ui.global::<Store>()
+                .set_history_info(info_text(&item).into());
+            ui.global::<Store>().set_history_text(item.text.into());
+        });
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_save_history_text(move |id, text| {
+        let ui = ui_handle.unwrap();
+        let id = match parse_id(&ui, &id) {
+            Some(id) => id,
+            None => return,
+        };
+
+        let (ui, text) = (ui.as_weak(), text.to_string());
+        spawn(async move {
+            match db::history::update_text(id, &text, Local::now().timestamp()).await {
+                Ok(_) => {
+                    async_message_success(ui.clone(), tr("保存成功"));
+                    reload(ui);
+                }
+                Err(e) => {
+                    async_message_warn(ui, format!("{}. {}: {e:?}", tr("保存失败"), tr("原因")))
+                }
+            }
+        });
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_reopen_history(move |id| {
+        let ui = ui_handle.unwrap();
+        let id = match parse_id(&ui, &id) {
+            Some(id) => id,
+            None => return,
+        };
+
+        with_history(ui.as_weak(), id, |ui, item, output| {
+            v2t::show_output(ui, &item.path, output, &item.text);
+        });
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_export_history(move |id| {
+        let ui = ui_handle.unwrap();
+        let id = match parse_id(&ui, &id) {
+            Some(id) => id,
+            None => return,
+        };
+
+        with_history(ui.as_weak(), id, |ui, item, output| {
+            v2t::save_output_as(ui, &item.path, &output, &item.text);
+        });
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_remove_history(move |id| {
+        let ui = ui_handle.unwrap();
+        let id = match parse_id(&ui, &id) {
+            Some(id) => id,
+            None => return,
+        };
+
+        ui.global::<Store>().set_history_info("".into());
+        ui.global::<Store>().set_history_text("".into());
+
+        let ui = ui.as_weak();
+        spawn(async move {
+            match db::history::delete(id).await {
+                Ok(_) => {
+                    async_message_success(ui.clone(), tr("删除成功"));
+                    reload(ui);
+                }
+                Err(e) => {
+                    async_message_warn(ui, format!("{}. {}: {e:?}", tr("删除失败"), tr("原因")))
+                }
+            }
+        });
+    });
+
+    ui.global::<Logic>().invoke_search_history("".into());
+}
+
+/// Saves a finished job in the background. The source file is hashed, so that a moved
+/// or renamed file can still be matched with its transcript. The job is saved without a
+/// hash when the file can no longer be read.
+pub fn record(
+    ui: Weak<AppWindow>,
+    audio_path: &str,
+    model_name: &str,
+    options: &TranscribeOptions,
+    output: &TranscriberOutput,
+) {
+    let (audio_path, model_name, options, output) = (
+        audio_path.to_string(),
+        model_name.to_string(),
+        options.clone(),
+        output.clone(),
+    );
+
+    spawn(async move {
+        let result = async {
+            let item = tokio::task::spawn_blocking(move || {
+                new_history(&audio_path, &model_name, &options, &output)
+            })
+            .await??;
+            db::history::insert(&item).await
+        }
+        .await;
+
+        match result {
+            Ok(_) => reload(ui),
+            Err(e) => log::warn!("{e:?}"),
+        }
+    });
+}
+
+fn new_history(
+    audio_path: &str,
+    model_name: &str,
+    options: &TranscribeOptions,
+    output: &TranscriberOutput,
+) -> Result<History> {
+    let duration_ms = match audio_parser::probe_audio(audio_path)
+        .ok()
+        .and_then(|info| info.duration_ms)
+    {
+        Some(duration_ms) => duration_ms as i64,
+        None => output.get_end_timestamp(),
+    };
+
+    let file_hash = match util::fs::sha256(audio_path) {
+        Ok(hash) => hash,
+        Err(e) => {
+            log::warn!("hash {audio_path} failed, the job is saved without it: {e:?}");
+            String::new()
+        }
+    };
+
+    let now = Local::now().timestamp();
+    Ok(History {
+        path: audio_path.to_string(),
+        file_hash,
+        model: model_name.to_string(),
+        options: serde_json::to_string(options)?,
+        duration_ms,
+        created_at: now,
+        updated_at: now,
+        output: serde_json::to_string(output)?,
+        text: output.get_text().to_string(),
+        ..Default::default()
+    })
+}
+
+// Shows the list again, keeping the search the user typed
+fn reload(ui: Weak<AppWindow>) {
+    let _ = slint::invoke_from_event_loop(move || {
+        let ui = ui.unwrap();
+        let query = ui.global::<Store>().get_history_query();
+        ui.global::<Logic>().invoke_search_history(query);
+    });
+}
+
+// Loads the whole job, and hands it with its parsed output to `f` on the UI thread
+fn with_history(
+    ui: Weak<AppWindow>,
+    id: i64,
+    f: impl FnOnce(&AppWindow, History, TranscriberOutput) + Send + 'static,
+) {
+    spawn(async move {
+        let item = match db::history::select(id).await {
+            Ok(Some(item)) => item,
+            Ok(None) => {
+                async_message_warn(ui, tr("记录不存在"));
+                return;
+            }
+            Err(e) => {
+                async_message_warn(ui, format!("{}. {}: {e:?}", tr("加载失败"), tr("原因")));
+                return;
+            }
+        };
+
+        let _ = slint::invoke_from_event_loop(move || {
+            let ui = ui.unwrap();
+            match serde_json::from_str::<TranscriberOutput>(&item.output) {
+                Ok(output) => f(&ui, item, output),
+                Err(e) => {
+                    message_warn!(ui, format!("{}. {}: {e:?}", tr("加载失败"), tr("原因")));
+                }
+            }
+        });
+    });
+}
+
+fn parse_id(ui: &AppWindow, id: &str) -> Option<i64> {
+    match id.parse::<i64>() {
+        Ok(id) => Some(id),
+        Err(_) => {
+            message_warn!(ui, tr("请先选择一条记录"));
+            None
+        }
+    }
+}
+
+fn set_items(ui: &AppWindow, items: Vec<History>) {
+    let items: Vec<HistoryItem> = items.iter().map(history_item).collect();
+
+    ui.global::<Store>()
+        .get_history_items()
+        .as_any()
+        .downcast_ref::<VecModel<HistoryItem>>()
+        .expect("We know we set a VecModel earlier")
+        .set_vec(items);
+}
+
+fn history_item(item: &History) -> HistoryItem {
+    HistoryItem {
+        id: item.id.to_string().into(),
+        name: util::fs::filename(&item.path).unwrap_or_default().into(),
+        model: item.model.clone().into(),
+        time: util::time::local_from_timestamp(item.created_at, "%Y-%m-%d %H:%M")
+            .unwrap_or_default()
+            .into(),
+        duration: util::time::from_timestamp(item.duration_ms / 1000, "%H:%M:%S")
+            .unwrap_or_default()
+            .into(),
+        summary: item.text.lines().next().unwrap_or_default().trim().into(),
+    }
+}
+
+fn info_text(item: &History) -> String {
+    let mut items = vec![item.path.clone(), item.model.clone()];
+
+    if let Ok(options) = serde_json::from_str::<TranscribeOptions>(&item.options) {
+        items.push(options.language.unwrap_or("auto".to_string()));
+        if options.translate {
+            items.push(tr("翻译为英文"));
+        }
+    }
+
+    if item.updated_at != item.created_at {
+        if let Ok(time) = util::time::local_from_timestamp(item.updated_at, "%Y-%m-%d %H:%M") {
+            items.push(format!("{}: {time}", tr("已编辑")));
+        }
+    }
+
+    items.join(" | ")
+}
@@ -1,6 +1,10 @@
 pub mod about;
 pub mod base;
+pub mod batch;
+pub mod bundle;
 pub mod clipboard;
+pub mod download;
+pub mod history;
 pub mod message;
 pub mod model;
 pub mod ok_cancel_dialog;
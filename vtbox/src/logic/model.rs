@@ -1,24 +1,40 @@
-use crate::slint_generatedAppWindow::{AppWindow, Logic, ModelItem, Store};
+use super::download;
+use crate::slint_generatedAppWindow::{AppWindow, Logic, ModelInfoDialog, ModelItem, Store};
 use crate::util::translator::tr;
-use crate::{config, message::async_message_warn, util};
+use crate::{config, model_cache, util};
 use crate::{message_info, message_success, message_warn};
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use native_dialog::FileDialog;
-use slint::{ComponentHandle, Model, SharedString, VecModel};
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel};
+use std::collections::HashSet;
 use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
 use tokio::task::spawn;
-use transcribe::model_handler;
+use transcribe::bundle::{self, BundleManifest, ImportedModel};
+use transcribe::catalog::{Catalog, CatalogEntry, Languages, ModelKind};
+use transcribe::manifest::{Checksum, Manifest, Verification};
+use transcribe::model_handler::{self, DownloadOptions, DownloadProgress, ProxyOptions};
+use transcribe::model_header::{ModelHeader, WhisperHparams};
 use uuid::Uuid;
 
-const PREDEFINED_MODELS_V2T: [&str; 5] = [
-    "ggml-tiny.bin",
-    "ggml-base.bin",
-    "ggml-small.bin",
-    "ggml-medium.bin",
-    "ggml-large.bin",
-];
+lazy_static! {
+    // The bundled catalog merged with the user's one, reloaded by "refresh"
+    static ref CATALOG: Mutex<Catalog> = Mutex::new(load_catalog());
+
+    // Models whose hash did not match the manifest when they were last verified
+    static ref MISMATCHED: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+
+    // Serializes the read-modify-write of the manifest files
+    static ref MANIFEST_LOCK: Mutex<()> = Mutex::new(());
+}
 
 pub fn init(ui: &AppWindow) {
+    ui.global::<Store>()
+        .set_v2t_models(ModelRc::new(VecModel::default()));
+    init_filters(ui, 0);
     init_model(ui, 0);
 
     let ui_handle = ui.as_weak();
@@ -35,18 +51,17 @@ pub fn init(ui: &AppWindow) {
                     continue;
                 }
 
-                if item.status == "Undownload" {
+                if item.status == "Undownload"
+                    || item.status == "Failed"
+                    || download::is_downloading(type_index, &item.name)
+                {
                     message_warn!(&ui, tr("无法删除，文件不存在"));
                     break;
                 }
 
-                let file = format!(
-                    "{}/{}/{}",
-                    config::cache_dir(),
-                    model_relative_path(type_index),
-                    item.name
-                );
-                let _ = fs::remove_file(file);
+                if let Err(e) = remove_model(type_index, &item.name) {
+                    log::warn!("{e:?}");
+                }
 
                 init_model(&ui, type_index);
                 message_success!(ui, tr("删除成功"));
@@ -58,6 +73,14 @@ pub fn init(ui: &AppWindow) {
     ui.global::<Logic>().on_refresh_model(move |type_index| {
         let ui = ui_handle.unwrap();
 
+        *CATALOG.lock().unwrap() = load_catalog();
+        init_filters(&ui, type_index);
+        init_model(&ui, type_index);
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_filter_model(move |type_index| {
+        let ui = ui_handle.unwrap();
         init_model(&ui, type_index);
     });
 
@@ -65,52 +88,80 @@ pub fn init(ui: &AppWindow) {
     ui.global::<Logic>()
         .on_switch_model_type(move |type_index| {
             let ui = ui_handle.unwrap();
+            init_filters(&ui, type_index);
             init_model(&ui, type_index);
         });
 
     let ui_handle = ui.as_weak();
-    ui.global::<Logic>()
-        .on_download_model(move |type_index, uuid| {
-            if uuid.is_empty() {
-                return;
-            }
+    ui.global::<Logic>().on_verify_models(move |type_index| {
+        let ui = ui_handle.unwrap();
+        message_info!(&ui, tr("正在校验..."));
+
+        let ui = ui.as_weak();
+        spawn(async move {
+            let result = match tokio::task::spawn_blocking(move || verify_models(type_index)).await
+            {
+                Ok(result) => result,
+                Err(e) => Err(e.into()),
+            };
 
-            let ui = ui_handle.unwrap();
+            let _ = slint::invoke_from_event_loop(move || {
+                let ui = ui.unwrap();
+                if type_index == ui.get_model_type_index() {
+                    init_model(&ui, type_index);
+                }
 
-            let model = match get_model_data(&ui, &uuid) {
-                Some(v) => v,
-                _ => {
-                    message_warn!(&ui, tr("内部错误，请刷新列表"));
+                let results = match result {
+                    Ok(results) => results,
+                    Err(e) => {
+                        message_warn!(&ui, format!("{}. {}: {e:?}", tr("校验失败"), tr("原因")));
+                        return;
+                    }
+                };
+
+                let names = |verification| {
+                    results
+                        .iter()
+                        .filter(|(_, v)| *v == verification)
+                        .map(|(name, _)| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+
+                let (mismatched, unverified, unknown) = (
+                    names(Verification::Mismatch),
+                    names(Verification::Unverified),
+                    names(Verification::Unknown),
+                );
+                if !mismatched.is_empty() {
+                    message_warn!(&ui, format!("{}: {mismatched}", tr("模型文件已损坏")));
+                } else if !unverified.is_empty() {
+                    message_info!(&ui, format!("{}: {unverified}", tr("没有可信的校验和")));
+                } else if !unknown.is_empty() {
+                    message_info!(&ui, format!("{}: {unknown}", tr("没有校验和，无法校验")));
+                } else {
+                    message_success!(&ui, tr("校验成功"));
+                }
+            });
+        });
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>()
+        .on_show_model_info(move |type_index, uuid| {
+            let ui = ui_handle.unwrap();
+            let item = match get_model_data(&ui, &uuid) {
+                Some(item) => item,
+                None => {
+                    message_info!(&ui, tr("请先选择一个模型"));
                     return;
                 }
             };
-            let name = model.name;
 
-            if !is_in_predefined_models(type_index, &name) {
-                message_info!(&ui, tr("不支持下载"));
-                return;
-            }
-
-            message_info!(&ui, tr("正在下载..."));
-
-            let (ui, name) = (ui.as_weak(), name.to_string());
-            spawn(async move {
-                match inner_download_model(type_index, &name).await {
-                    Err(e) => async_message_warn(
-                        ui.clone(),
-                        format!("{}. {}: {e:?}", tr("下载失败"), tr("原因")),
-                    ),
-                    _ => {
-                        let _ = slint::invoke_from_event_loop(move || {
-                            let ui = ui.clone().unwrap();
-                            if type_index == ui.get_model_type_index() {
-                                init_model(&ui, type_index);
-                            }
-
-                            message_success!(ui, tr("下载成功"));
-                        });
-                    }
-                }
+            ui.global::<Store>().set_model_info_dialog(ModelInfoDialog {
+                show: true,
+                title: item.name.clone(),
+                text: model_info_text(type_index, &item.name).into(),
             });
         });
 
@@ -119,24 +170,15 @@ pub fn init(ui: &AppWindow) {
         let ui = ui_handle.unwrap();
 
         match FileDialog::new().set_location("~").show_open_single_file() {
-            Ok(Some(file)) => {
-                let path = format!(
-                    "{}/{}/{}",
-                    config::cache_dir(),
-                    model_relative_path(type_index),
-                    file.file_name().unwrap().to_str().unwrap(),
-                );
-
-                match fs::copy(file, path) {
-                    Err(e) => {
-                        message_warn!(&ui, format!("{}. {}: {e:?}", tr("导入失败"), tr("原因")));
-                    }
-                    _ => {
-                        init_model(&ui, type_index);
-                        message_success!(&ui, tr("导入成功"));
-                    }
+            Ok(Some(file)) => match import_model(type_index, &file) {
+                Err(e) => {
+                    message_warn!(&ui, format!("{}. {}: {e:?}", tr("导入失败"), tr("原因")));
                 }
-            }
+                _ => {
+                    init_model(&ui, type_index);
+                    message_success!(&ui, tr("导入成功"));
+                }
+            },
             Err(e) => {
                 message_warn!(&ui, format!("{}. {}: {e:?}", tr("导入失败"), tr("原因")));
             }
@@ -146,7 +188,7 @@ pub fn init(ui: &AppWindow) {
     });
 }
 
-fn init_model(ui: &AppWindow, type_index: i32) {
+pub fn init_model(ui: &AppWindow, type_index: i32) {
     let cache_dir = config::cache_dir();
     let _ = std::fs::create_dir_all(format!("{}/{}", cache_dir, model_relative_path(0)));
     let _ = std::fs::create_dir_all(format!("{}/{}", cache_dir, model_relative_path(1)));
@@ -175,20 +217,305 @@ pub fn model_relative_path(type_index: i32) -> String {
     }
 }
 
-async fn inner_download_model(type_index: i32, name: &str) -> Result<()> {
-    let proxy_config = config::socks5();
+/// Downloads a model of the catalog, resuming an interrupted download of it.
+pub async fn download_model(
+    type_index: i32,
+    name: &str,
+    on_progress: impl FnMut(DownloadProgress),
+) -> Result<()> {
     let path = format!(
         "{}/{}",
         config::cache_dir(),
         model_relative_path(type_index)
     );
-    let proxy_info = if proxy_config.enabled {
-        Some((proxy_config.url.as_str(), proxy_config.port))
-    } else {
-        None
+
+    let entry = match catalog_entry(type_index, name) {
+        Some(entry) => entry,
+        None => bail!("{name} is not in the model catalog"),
+    };
+
+    // Pinned by the catalog or by a verified download. Without one, the checksum the
+    // server announces only tells a broken transfer, not a tampered file.
+    let expected = match entry.checksum {
+        Some(checksum) => Some(checksum),
+        None => load_manifest(type_index)?.pinned(name).cloned(),
+    };
+    let verified = expected.is_some();
+    let checksum = model_handler::download_model_with_progress(
+        &path,
+        name,
+        &entry.url,
+        &download_options(),
+        expected,
+        on_progress,
+    )
+    .await?;
+
+    if !verified {
+        log::warn!("{name} has no pinned checksum, it is installed unverified");
+    }
+
+    update_manifest(type_index, |manifest| match verified {
+        true => manifest.insert(name, checksum),
+        false => manifest.insert_unverified(name, checksum),
+    })?;
+    MISMATCHED.lock().unwrap().remove(name);
+    Ok(())
+}
+
+/// The mirrors, proxy and timeouts of the configuration.
+pub fn download_options() -> DownloadOptions {
+    let (download, proxy) = (config::download(), config::proxy());
+    let timeout = |secs| match secs {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    };
+
+    DownloadOptions {
+        mirrors: download.mirrors,
+        proxy: match proxy.enabled {
+            true => Some(ProxyOptions {
+                url: format!("{}://{}:{}", proxy.scheme, proxy.url, proxy.port),
+                username: proxy.username,
+                password: proxy.password,
+                no_proxy: proxy.no_proxy,
+            }),
+            false => None,
+        },
+        connect_timeout: timeout(download.connect_timeout_secs),
+        read_timeout: timeout(download.read_timeout_secs),
+    }
+}
+
+/// Copies a model file into the model directory and pins its checksum.
+pub fn import_model(type_index: i32, file: &Path) -> Result<()> {
+    let name = match file.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name.to_string(),
+        None => bail!("invalid file: {}", file.display()),
+    };
+
+    let dir = format!(
+        "{}/{}",
+        config::cache_dir(),
+        model_relative_path(type_index)
+    );
+    fs::create_dir_all(&dir)?;
+
+    // Anything but a model whisper.cpp loads is refused before it is copied
+    if model_kind(type_index) == ModelKind::VoiceToText {
+        ModelHeader::read(file)?
+            .whisper()
+            .with_context(|| format!("can't import {}", file.display()))?;
+    }
+
+    let path = Path::new(&dir).join(&name);
+    fs::copy(file, &path).with_context(|| format!("can't import {}", file.display()))?;
+
+    let checksum = Checksum::of_file(&path)?;
+    update_manifest(type_index, |manifest| manifest.insert(&name, checksum))?;
+    MISMATCHED.lock().unwrap().remove(&name);
+    Ok(())
+}
+
+/// The downloaded and imported models of the directory, sorted by name.
+pub fn installed_models(type_index: i32) -> Result<Vec<String>> {
+    let dir = format!(
+        "{}/{}",
+        config::cache_dir(),
+        model_relative_path(type_index)
+    );
+
+    let mut names = vec![];
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(names),
+        Err(e) => return Err(e.into()),
     };
 
-    model_handler::download_model(&path, name, proxy_info).await
+    for entry in entries {
+        let path = entry?.path();
+        match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if path.is_file() && !model_handler::is_partial_download(name) => {
+                names.push(name.to_string())
+            }
+            _ => (),
+        }
+    }
+
+    names.sort();
+    Ok(names)
+}
+
+/// Packs models into a bundle for the machines without internet. A model that does
+/// not match the checksum pinned at download or import is not exported.
+pub fn export_bundle(type_index: i32, names: &[String], archive: &Path) -> Result<BundleManifest> {
+    if names.is_empty() {
+        bail!("no model to export");
+    }
+
+    let dir = Path::new(&config::cache_dir()).join(model_relative_path(type_index));
+    let paths: Vec<_> = names.iter().map(|name| dir.join(name)).collect();
+    if let Some(name) = names.iter().find(|name| !dir.join(name).is_file()) {
+        bail!("{name} is not downloaded");
+    }
+
+    let bundle = bundle::export_bundle(archive, &model_relative_path(type_index), &paths)?;
+
+    let manifest = load_manifest(type_index)?;
+    for (name, checksum) in bundle.models.iter() {
+        let mismatched = manifest.get(name).is_some_and(|pinned| {
+            pinned.size != checksum.size || !pinned.sha256.eq_ignore_ascii_case(&checksum.sha256)
+        });
+
+        if mismatched {
+            let _ = fs::remove_file(archive);
+            MISMATCHED.lock().unwrap().insert(name.clone());
+            bail!("{name} does not match its checksum, it is not exported");
+        }
+    }
+    Ok(bundle)
+}
+
+/// Verifies a bundle and installs its models into the directory they were exported
+/// from. Returns the model type of the bundle.
+///
+/// The checksums of a bundle only tell a broken copy, so a model is pinned as verified
+/// only when the catalog or an earlier verified install vouches for it, and refused when
+/// the catalog pins another checksum.
+pub fn import_bundle(archive: &Path) -> Result<(i32, Vec<ImportedModel>)> {
+    let manifest = bundle::read_manifest(archive)?;
+    let type_index = match (0..2).find(|i| model_relative_path(*i) == manifest.directory) {
+        Some(type_index) => type_index,
+        None => bail!("unknown model directory {}", manifest.directory),
+    };
+
+    for (name, checksum) in manifest.models.iter() {
+        let pinned = catalog_entry(type_index, name).and_then(|entry| entry.checksum);
+        if pinned.is_some_and(|pinned| pinned != *checksum) {
+            bail!("{name} does not match its checksum in the model catalog");
+        }
+    }
+
+    // Anything but a model whisper.cpp loads is refused before it is installed
+    let dir = Path::new(&config::cache_dir()).join(model_relative_path(type_index));
+    let (_, imported) = bundle::import_bundle(archive, &dir, |_, path| {
+        if model_kind(type_index) == ModelKind::VoiceToText {
+            ModelHeader::read(path)?.whisper()?;
+        }
+        Ok(())
+    })?;
+
+    update_manifest(type_index, |manifest| {
+        for model in imported.iter() {
+            let pinned = catalog_entry(type_index, &model.name)
+                .and_then(|entry| entry.checksum)
+                .or_else(|| manifest.pinned(&model.installed_as).cloned());
+
+            match pinned == Some(model.checksum.clone()) {
+                true => manifest.insert(&model.installed_as, model.checksum.clone()),
+                false => manifest.insert_unverified(&model.installed_as, model.checksum.clone()),
+            }
+        }
+    })?;
+
+    for model in imported.iter() {
+        MISMATCHED.lock().unwrap().remove(&model.installed_as);
+    }
+    Ok((type_index, imported))
+}
+
+/// Hashes every model of the directory and compares it with the manifest.
+pub fn verify_models(type_index: i32) -> Result<Vec<(String, Verification)>> {
+    let dir = format!(
+        "{}/{}",
+        config::cache_dir(),
+        model_relative_path(type_index)
+    );
+    let manifest = load_manifest(type_index)?;
+
+    let mut results = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if path.is_file() && !model_handler::is_partial_download(name) => {
+                name.to_string()
+            }
+            _ => continue,
+        };
+
+        let verification = manifest.verify(&name, &path, false)?;
+        if verification == Verification::Mismatch {
+            MISMATCHED.lock().unwrap().insert(name.clone());
+        } else {
+            MISMATCHED.lock().unwrap().remove(&name);
+        }
+        results.push((name, verification));
+    }
+
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(results)
+}
+
+/// "Downloaded", "Imported", "Mismatch", "Unverified" or "Undownload". Only the size is
+/// compared with the manifest here, `verify_models` compares the hashes.
+pub fn model_status(type_index: i32, name: &str) -> &'static str {
+    let path = Path::new(&config::cache_dir())
+        .join(model_relative_path(type_index))
+        .join(name);
+    if !path.is_file() {
+        return "Undownload";
+    }
+
+    let verified_mismatch = MISMATCHED.lock().unwrap().contains(name);
+    let verification = load_manifest(type_index)
+        .and_then(|manifest| manifest.verify(name, &path, true))
+        .ok();
+
+    if verified_mismatch || verification == Some(Verification::Mismatch) {
+        "Mismatch"
+    } else if verification == Some(Verification::Unverified) {
+        "Unverified"
+    } else if is_in_catalog(type_index, name) {
+        "Downloaded"
+    } else {
+        "Imported"
+    }
+}
+
+pub fn manifest_path(type_index: i32) -> String {
+    let dir = if type_index == 0 { "v2t" } else { "t2v" };
+    format!("{}/{dir}/manifest.json", config::cache_dir())
+}
+
+fn load_manifest(type_index: i32) -> Result<Manifest> {
+    let _lock = MANIFEST_LOCK.lock().unwrap();
+    Manifest::load(manifest_path(type_index))
+}
+
+fn update_manifest(type_index: i32, f: impl FnOnce(&mut Manifest)) -> Result<()> {
+    let _lock = MANIFEST_LOCK.lock().unwrap();
+    let mut manifest = Manifest::load(manifest_path(type_index))?;
+    f(&mut manifest);
+    manifest.save()
+}
+
+/// Removes the model file. The checksum of a catalog model is kept for its next
+/// download, the one of an imported model is forgotten.
+pub fn remove_model(type_index: i32, name: &str) -> Result<()> {
+    let file = format!(
+        "{}/{}/{}",
+        config::cache_dir(),
+        model_relative_path(type_index),
+        name
+    );
+    fs::remove_file(&file).with_context(|| format!("can't remove {file}"))?;
+    model_cache::remove(&file);
+    MISMATCHED.lock().unwrap().remove(name);
+
+    if !is_in_catalog(type_index, name) {
+        update_manifest(type_index, |manifest| manifest.remove(name))?;
+    }
+    Ok(())
 }
 
 fn model_items(ui: &AppWindow, type_index: i32) -> Result<Vec<ModelItem>> {
@@ -204,19 +531,14 @@ fn model_items(ui: &AppWindow, type_index: i32) -> Result<Vec<ModelItem>> {
             let path = entry.path();
             if path.is_file() {
                 let name = path.file_name()?.to_str()?;
-                let size = util::fs::pretty_size(entry.metadata().ok()?.len());
-                let status = if is_in_predefined_models(type_index, name) {
-                    "Downloaded"
-                } else {
-                    "Imported"
-                };
+                if model_handler::is_partial_download(name) {
+                    return None;
+                }
 
-                Some(ModelItem {
-                    uuid: Uuid::new_v4().to_string().into(),
-                    name: name.into(),
-                    size: size.into(),
-                    status: status.into(),
-                })
+                let mut item = model_item(type_index, name);
+                item.size = util::fs::pretty_size(entry.metadata().ok()?.len()).into();
+                item.status = model_status(type_index, name).into();
+                Some(item)
             } else {
                 None
             }
@@ -225,26 +547,259 @@ fn model_items(ui: &AppWindow, type_index: i32) -> Result<Vec<ModelItem>> {
 
     set_combobox_models(ui, type_index, &models);
     append_undownload_model(type_index, &mut models);
+
+    let filter = ModelFilter::new(ui);
+    models.retain(|item| filter.matches(item));
     Ok(models)
 }
 
-fn is_in_predefined_models(type_index: i32, name: &str) -> bool {
+fn model_kind(type_index: i32) -> ModelKind {
     if type_index == 0 {
-        for item in PREDEFINED_MODELS_V2T {
-            if item == name {
-                return true;
+        ModelKind::VoiceToText
+    } else {
+        ModelKind::TextToVoice
+    }
+}
+
+/// The user's catalog, merged over the bundled one. It is a JSON array of entries
+/// next to the configuration file.
+pub fn catalog_path() -> String {
+    Path::new(&config::conf_path())
+        .with_file_name("catalog.json")
+        .to_string_lossy()
+        .to_string()
+}
+
+fn load_catalog() -> Catalog {
+    match Catalog::load(Path::new(&catalog_path())) {
+        Ok(catalog) => catalog,
+        Err(e) => {
+            log::warn!("{e:?}");
+            Catalog::bundled()
+        }
+    }
+}
+
+pub fn catalog_entries(type_index: i32) -> Vec<CatalogEntry> {
+    CATALOG
+        .lock()
+        .unwrap()
+        .of_kind(model_kind(type_index))
+        .cloned()
+        .collect()
+}
+
+pub fn catalog_entry(type_index: i32, name: &str) -> Option<CatalogEntry> {
+    CATALOG
+        .lock()
+        .unwrap()
+        .get(model_kind(type_index), name)
+        .cloned()
+}
+
+pub fn is_in_catalog(type_index: i32, name: &str) -> bool {
+    catalog_entry(type_index, name).is_some()
+}
+
+// "f16" stands for the models that are not quantized
+pub fn quantization_name(entry: &CatalogEntry) -> &str {
+    entry.quantization.as_deref().unwrap_or("f16")
+}
+
+/// Family, languages and quantization of a model, from its catalog entry, or else from
+/// the header of its file for the imported ones.
+pub fn model_attributes(type_index: i32, name: &str) -> Option<(String, &'static str, String)> {
+    if let Some(entry) = catalog_entry(type_index, name) {
+        let quantization = quantization_name(&entry).to_string();
+        return Some((entry.family, entry.languages.name(), quantization));
+    }
+
+    let path = Path::new(&config::cache_dir())
+        .join(model_relative_path(type_index))
+        .join(name);
+    match ModelHeader::read(&path) {
+        Ok(ModelHeader::Ggml(hparams)) => Some((
+            hparams.model_type().to_string(),
+            hparams_languages(&hparams).name(),
+            hparams.quantization().unwrap_or("-").to_string(),
+        )),
+        _ => None,
+    }
+}
+
+// An undownloaded model, with the attributes of its catalog entry
+fn model_item(type_index: i32, name: &str) -> ModelItem {
+    let entry = catalog_entry(type_index, name);
+    let (family, languages, quantization) =
+        model_attributes(type_index, name).unwrap_or(("-".to_string(), "-", "-".to_string()));
+    ModelItem {
+        uuid: Uuid::new_v4().to_string().into(),
+        name: name.into(),
+        family: family.into(),
+        languages: languages.into(),
+        quantization: quantization.into(),
+        size: entry
+            .as_ref()
+            .map_or("-".to_string(), |e| format!("~{}M", e.size_mb))
+            .into(),
+        status: "Undownload".into(),
+        progress: 0.0,
+        detail: "".into(),
+    }
+}
+
+fn hparams_languages(hparams: &WhisperHparams) -> Languages {
+    match hparams.is_multilingual() {
+        true => Languages::Multilingual,
+        false => Languages::English,
+    }
+}
+
+/// What the catalog and the header of the file tell about a model, one item per line.
+pub fn model_info_text(type_index: i32, name: &str) -> String {
+    let path = Path::new(&config::cache_dir())
+        .join(model_relative_path(type_index))
+        .join(name);
+    let mut lines = vec![];
+
+    if let Some(entry) = catalog_entry(type_index, name) {
+        lines.push(format!("{}: {}", tr("系列"), entry.family));
+        lines.push(format!("{}: {}", tr("语言"), entry.languages.name()));
+        lines.push(format!("{}: {}", tr("量化"), quantization_name(&entry)));
+        lines.push(format!("URL: {}", entry.url));
+    }
+
+    let size = match fs::metadata(&path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => {
+            lines.push(tr("未下载"));
+            return lines.join("\n");
+        }
+    };
+    lines.push(format!("{}: {}", tr("路径"), path.display()));
+    lines.push(format!("{}: {}", tr("大小"), util::fs::pretty_size(size)));
+
+    match ModelHeader::read(&path) {
+        Ok(ModelHeader::Ggml(hparams)) => {
+            lines.push(format!("{}: ggml", tr("格式")));
+            lines.push(format!("{}: {}", tr("类型"), hparams.model_type()));
+            lines.push(format!(
+                "{}: {}",
+                tr("语言"),
+                hparams_languages(&hparams).name()
+            ));
+            lines.push(format!(
+                "{}: {}",
+                tr("量化"),
+                hparams.quantization().unwrap_or("-")
+            ));
+
+            if let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(hparams) {
+                lines.extend(fields.iter().map(|(key, value)| format!("{key}: {value}")));
             }
         }
+        Ok(ModelHeader::Gguf {
+            version,
+            tensor_count,
+            architecture,
+        }) => {
+            lines.push(format!("{}: gguf v{version}", tr("格式")));
+            lines.push(format!(
+                "{}: {}",
+                tr("架构"),
+                architecture.as_deref().unwrap_or("-")
+            ));
+            lines.push(format!("tensors: {tensor_count}"));
+            lines.push(tr("whisper.cpp 无法加载 GGUF 模型"));
+        }
+        Err(e) => lines.push(format!("{}: {e:#}", tr("无法解析模型文件头"))),
+    }
+
+    lines.join("\n")
+}
+
+// The choices of the filter comboboxes, "All" first
+fn init_filters(ui: &AppWindow, type_index: i32) {
+    let entries = catalog_entries(type_index);
+
+    let mut families = vec![tr("全部")];
+    let mut quantizations = vec![tr("全部")];
+    for entry in entries.iter() {
+        if !families.contains(&entry.family) {
+            families.push(entry.family.clone());
+        }
+
+        let quantization = quantization_name(entry).to_string();
+        if !quantizations.contains(&quantization) {
+            quantizations.push(quantization);
+        }
+    }
+    quantizations[1..].sort();
+
+    let languages = vec![
+        tr("全部"),
+        Languages::Multilingual.name().to_string(),
+        Languages::English.name().to_string(),
+    ];
+
+    let store = ui.global::<Store>();
+    store.set_model_families(string_model(families));
+    store.set_model_family_index(0);
+    store.set_model_languages(string_model(languages));
+    store.set_model_language_index(0);
+    store.set_model_quantizations(string_model(quantizations));
+    store.set_model_quantization_index(0);
+}
+
+fn string_model(items: Vec<String>) -> ModelRc<SharedString> {
+    let items: Vec<SharedString> = items.into_iter().map(|item| item.into()).collect();
+    ModelRc::new(VecModel::from(items))
+}
+
+// What the filter comboboxes of the model panel select, `None` for "All"
+struct ModelFilter {
+    family: Option<SharedString>,
+    languages: Option<SharedString>,
+    quantization: Option<SharedString>,
+}
+
+impl ModelFilter {
+    fn new(ui: &AppWindow) -> Self {
+        let store = ui.global::<Store>();
+        let selected = |items: ModelRc<SharedString>, index: i32| match index {
+            index if index > 0 => items.row_data(index as usize),
+            _ => None,
+        };
+
+        Self {
+            family: selected(store.get_model_families(), store.get_model_family_index()),
+            languages: selected(
+                store.get_model_languages(),
+                store.get_model_language_index(),
+            ),
+            quantization: selected(
+                store.get_model_quantizations(),
+                store.get_model_quantization_index(),
+            ),
+        }
     }
 
-    false
+    fn matches(&self, item: &ModelItem) -> bool {
+        [
+            (&self.family, &item.family),
+            (&self.languages, &item.languages),
+            (&self.quantization, &item.quantization),
+        ]
+        .iter()
+        .all(|(selected, value)| selected.as_ref().is_none_or(|s| s == *value))
+    }
 }
 
 fn is_in_models(items: &[ModelItem], name: &str) -> bool {
     items.iter().any(|item| item.name == name)
 }
 
-fn get_model_data(ui: &AppWindow, uuid: &str) -> Option<ModelItem> {
+pub fn get_model_data(ui: &AppWindow, uuid: &str) -> Option<ModelItem> {
     ui.global::<Store>()
         .get_model_datas()
         .iter()
@@ -253,16 +808,11 @@ fn get_model_data(ui: &AppWindow, uuid: &str) -> Option<ModelItem> {
 
 fn append_undownload_model(type_index: i32, models: &mut Vec<ModelItem>) {
     let mut tmp_items = vec![];
-    if type_index == 0 {
-        for name in PREDEFINED_MODELS_V2T {
-            if !is_in_models(models, name) {
-                tmp_items.push(ModelItem {
-                    uuid: Uuid::new_v4().to_string().into(),
-                    name: name.into(),
-                    size: "-".into(),
-                    status: "Undownload".into(),
-                });
-            }
+    for entry in catalog_entries(type_index) {
+        if !is_in_models(models, &entry.name) {
+            let mut item = model_item(type_index, &entry.name);
+            download::show_download(type_index, &mut item);
+            tmp_items.push(item);
         }
     }
     models.append(&mut tmp_items);
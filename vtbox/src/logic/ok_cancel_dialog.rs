@@ -14,6 +14,9 @@ pub fn init(ui: &AppWindow) {
                     ui.global::<Logic>()
                         .invoke_remove_model(items[0].parse::<_>().unwrap(), items[1].into());
                 }
+                "remove-history" => {
+                    ui.global::<Logic>().invoke_remove_history(handle_uuid);
+                }
                 _ => (),
             }
         });
@@ -57,14 +57,52 @@ pub fn init(ui: &AppWindow) {
         );
         config.ui.language = setting_config.ui.language.to_string();
 
-        config.socks5.enabled = setting_config.proxy.enabled;
-        config.socks5.url = setting_config.proxy.url.to_string();
-        config.socks5.port = setting_config
+        config.proxy.enabled = setting_config.proxy.enabled;
+        config.proxy.scheme = setting_config.proxy.scheme.to_string();
+        config.proxy.url = setting_config.proxy.url.to_string();
+        config.proxy.port = setting_config
             .proxy
             .port
             .to_string()
             .parse()
             .unwrap_or(1080);
+        config.proxy.username = setting_config.proxy.username.to_string();
+        config.proxy.password = setting_config.proxy.password.to_string();
+        config.proxy.no_proxy = setting_config.proxy.no_proxy.to_string();
+
+        config.model.memory_budget_mb = setting_config
+            .model
+            .memory_budget
+            .to_string()
+            .parse()
+            .unwrap_or(4096);
+        config.model.preload = setting_config.model.preload;
+
+        config.download.concurrency = setting_config
+            .model
+            .download_concurrency
+            .to_string()
+            .parse()
+            .unwrap_or(2);
+        config.download.mirrors = setting_config
+            .model
+            .mirrors
+            .split(',')
+            .map(|mirror| mirror.trim().to_string())
+            .filter(|mirror| !mirror.is_empty())
+            .collect();
+        config.download.connect_timeout_secs = setting_config
+            .model
+            .connect_timeout
+            .to_string()
+            .parse()
+            .unwrap_or(10);
+        config.download.read_timeout_secs = setting_config
+            .model
+            .read_timeout
+            .to_string()
+            .parse()
+            .unwrap_or(60);
 
         match config::save(config) {
             Err(e) => {
@@ -81,7 +119,9 @@ pub fn init(ui: &AppWindow) {
 fn init_setting_dialog(ui: Weak<AppWindow>) {
     let ui = ui.unwrap();
     let ui_config = config::ui();
-    let socks5_config = config::socks5();
+    let proxy_config = config::proxy();
+    let model_config = config::model();
+    let download_config = config::download();
 
     let mut setting_dialog = ui.global::<Store>().get_setting_dialog_config();
     setting_dialog.ui.font_size = slint::format!("{}", ui_config.font_size);
@@ -90,9 +130,22 @@ fn init_setting_dialog(ui: Weak<AppWindow>) {
     setting_dialog.ui.win_height = slint::format!("{}", u32::max(ui_config.win_height, 600));
     setting_dialog.ui.language = ui_config.language.into();
 
-    setting_dialog.proxy.enabled = socks5_config.enabled;
-    setting_dialog.proxy.url = socks5_config.url.into();
-    setting_dialog.proxy.port = slint::format!("{}", socks5_config.port);
+    setting_dialog.proxy.enabled = proxy_config.enabled;
+    setting_dialog.proxy.scheme = proxy_config.scheme.into();
+    setting_dialog.proxy.url = proxy_config.url.into();
+    setting_dialog.proxy.port = slint::format!("{}", proxy_config.port);
+    setting_dialog.proxy.username = proxy_config.username.into();
+    setting_dialog.proxy.password = proxy_config.password.into();
+    setting_dialog.proxy.no_proxy = proxy_config.no_proxy.into();
+
+    setting_dialog.model.memory_budget = slint::format!("{}", model_config.memory_budget_mb);
+    setting_dialog.model.preload = model_config.preload;
+    setting_dialog.model.download_concurrency =
+        slint::format!("{}", download_config.concurrency.max(1));
+    setting_dialog.model.mirrors = download_config.mirrors.join(",").into();
+    setting_dialog.model.connect_timeout =
+        slint::format!("{}", download_config.connect_timeout_secs);
+    setting_dialog.model.read_timeout = slint::format!("{}", download_config.read_timeout_secs);
 
     ui.global::<Store>()
         .set_setting_dialog_config(setting_dialog);
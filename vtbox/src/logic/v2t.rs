@@ -1,33 +1,79 @@
+use super::history;
 use crate::slint_generatedAppWindow::{AppWindow, Logic, Store};
 use crate::util::translator::tr;
 use crate::{
     config,
-    message::async_message_warn,
-    model::model_relative_path,
-    transcribe::{model_handler, transcriber},
+    message::{async_message_info, async_message_warn},
+    model_cache,
+    transcribe::{
+        audio_parser,
+        export::{self, SubtitleFormat},
+        job::{AbortHandle, Aborted, JobControl},
+        options, transcriber,
+    },
     util,
 };
 use crate::{message_info, message_success, message_warn};
 use anyhow::Result;
 use chrono::Local;
 use native_dialog::FileDialog;
-use slint::{ComponentHandle, Model, SharedString};
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel};
+use std::fs;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use tokio::task::spawn;
 
 static IS_CONVERTING: AtomicBool = AtomicBool::new(false);
 
+lazy_static! {
+    // The last transcription, kept with its timestamps for "Save as"
+    static ref LAST_OUTPUT: Mutex<Option<transcriber::TranscriberOutput>> = Mutex::new(None);
+
+    // Stops the running transcription
+    static ref ABORT_HANDLE: Mutex<Option<AbortHandle>> = Mutex::new(None);
+}
+
+// Files longer than this get a warning when they are opened
+const LONG_AUDIO_MS: u64 = 2 * 60 * 60 * 1000;
+
+// "Save as" writes the plain text for this extension, and subtitles for the others
+const TEXT_EXTENSION: &str = "txt";
+
 pub fn init(ui: &AppWindow) {
+    init_languages(ui);
+
     let ui_handle = ui.as_weak();
     ui.global::<Logic>().on_open_audio_file(move || {
         let ui = ui_handle.unwrap();
+        let extensions = audio_parser::supported_extensions();
 
         match FileDialog::new()
             .set_location("~")
-            .add_filter("Audio", &["mp3", "wav"])
+            .add_filter("Audio/Video", &extensions)
             .show_open_single_file()
         {
-            Ok(Some(file)) => file.to_str().unwrap().into(),
+            Ok(Some(file)) => {
+                let path = file.to_str().unwrap();
+                match audio_parser::probe_audio(path) {
+                    Ok(info) => {
+                        ui.global::<Store>()
+                            .set_v2t_audio_info(audio_info_text(&info).into());
+
+                        if info.duration_ms.unwrap_or_default() > LONG_AUDIO_MS {
+                            message_info!(ui, tr("音频较长，转换需要较长时间"));
+                        }
+                    }
+                    Err(e) => {
+                        ui.global::<Store>().set_v2t_audio_info("".into());
+                        message_warn!(
+                            &ui,
+                            format!("{}. {}: {e:?}", tr("无法识别的音频文件"), tr("原因"))
+                        );
+                    }
+                }
+                path.into()
+            }
             Err(e) => {
                 message_warn!(
                     &ui,
@@ -41,7 +87,7 @@ pub fn init(ui: &AppWindow) {
 
     let ui_handle = ui.as_weak();
     ui.global::<Logic>()
-        .on_start_v2t(move |model_name, audio_path| {
+        .on_start_v2t(move |model_name, audio_path, language, translate| {
             let ui = ui_handle.unwrap();
 
             if IS_CONVERTING.load(Ordering::SeqCst) {
@@ -60,6 +106,10 @@ pub fn init(ui: &AppWindow) {
             }
 
             message_info!(ui, tr("正在转换..."));
+            ui.global::<Store>().set_v2t_detected_language("".into());
+            ui.global::<Store>().set_v2t_progress(0.0);
+            ui.global::<Store>().set_v2t_converting(true);
+            *LAST_OUTPUT.lock().unwrap() = None;
 
             let (ui, ui_timer, model_name, audio_path) = (
                 ui.as_weak(),
@@ -67,6 +117,39 @@ pub fn init(ui: &AppWindow) {
                 model_name.to_string(),
                 audio_path.to_string(),
             );
+            let mut options = config::transcribe();
+            options.language = parse_language(&language);
+            options.translate = translate;
+
+            let mut conf = config::config();
+            conf.transcribe = options.clone();
+            conf.model.last_used = model_name.clone();
+            if let Err(e) = config::save(conf) {
+                log::warn!("{e:?}");
+            }
+
+            let abort_handle = AbortHandle::new();
+            *ABORT_HANDLE.lock().unwrap() = Some(abort_handle.clone());
+
+            let ui_progress = ui.clone();
+            let control = JobControl::new()
+                .with_abort_handle(abort_handle)
+                .on_progress(move |progress| {
+                    let ui = ui_progress.clone();
+                    let _ = slint::invoke_from_event_loop(move || {
+                        let ui = ui.unwrap();
+                        if let Some(percent) = progress.percent {
+                            ui.global::<Store>().set_v2t_progress(percent / 100.0);
+                        }
+
+                        // Show the text as it comes in
+                        if let Some(segment) = progress.segment {
+                            let text = ui.global::<Store>().get_v2t_text();
+                            ui.global::<Store>()
+                                .set_v2t_text(format!("{text}{}", segment.text).into());
+                        }
+                    });
+                });
 
             IS_CONVERTING.store(true, Ordering::SeqCst);
 
@@ -96,31 +179,257 @@ pub fn init(ui: &AppWindow) {
                 }
             });
 
+            let job_options = options.clone();
             spawn(async move {
-                match inner_start_v2t(&model_name, &audio_path) {
+                match inner_start_v2t(&model_name, &audio_path, options, control).await {
+                    Err(e) if e.is::<Aborted>() => {
+                        async_message_info(ui.clone(), tr("已取消转换"));
+                    }
                     Err(e) => async_message_warn(
                         ui.clone(),
                         format!("{}. {}: {e:?}", tr("转换失败"), tr("原因")),
                     ),
-                    Ok(text) => {
+                    Ok(output) => {
+                        history::record(
+                            ui.clone(),
+                            &audio_path,
+                            &model_name,
+                            &job_options,
+                            &output,
+                        );
+
+                        let text = output.get_text().to_string();
+                        let language = detected_language_text(&output);
+                        *LAST_OUTPUT.lock().unwrap() = Some(output);
+
+                        let ui = ui.clone();
                         let _ = slint::invoke_from_event_loop(move || {
                             let ui = ui.clone().unwrap();
                             ui.global::<Store>().set_v2t_text(text.into());
+                            ui.global::<Store>()
+                                .set_v2t_detected_language(language.into());
                             message_success!(&ui, tr("转换成功"));
                         });
                     }
                 }
 
+                *ABORT_HANDLE.lock().unwrap() = None;
                 IS_CONVERTING.store(false, Ordering::SeqCst);
+
+                let _ = slint::invoke_from_event_loop(move || {
+                    let ui = ui.unwrap();
+                    ui.global::<Store>().set_v2t_converting(false);
+                });
             });
         });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_cancel_v2t(move || {
+        let ui = ui_handle.unwrap();
+
+        if let Some(handle) = ABORT_HANDLE.lock().unwrap().as_ref() {
+            handle.abort();
+            message_info!(ui, tr("正在取消..."));
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.global::<Logic>().on_save_v2t_as(move |audio_path| {
+        let ui = ui_handle.unwrap();
+
+        let output = LAST_OUTPUT.lock().unwrap().clone();
+        match output {
+            Some(output) if !ui.global::<Store>().get_v2t_text().is_empty() => {
+                let text = ui.global::<Store>().get_v2t_text();
+                save_output_as(&ui, &audio_path, &output, &text)
+            }
+            _ => {
+                message_warn!(ui, tr("没有可保存的内容"));
+            }
+        }
+    });
+}
+
+/// Asks where to save `output` as subtitles, next to the audio by default. A `.txt` file
+/// gets `text`, which the user may have edited, the subtitles keep the original segments.
+pub fn save_output_as(
+    ui: &AppWindow,
+    audio_path: &str,
+    output: &transcriber::TranscriberOutput,
+    text: &str,
+) {
+    let audio_path = Path::new(audio_path);
+    let filename = format!(
+        "{}.{}",
+        audio_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("subtitle"),
+        SubtitleFormat::Srt.extension()
+    );
+    let location = match audio_path.parent() {
+        Some(dir) if dir.is_dir() => dir,
+        _ => Path::new("~"),
+    };
+
+    let mut filters: Vec<(&str, [&str; 1])> = SubtitleFormat::all()
+        .iter()
+        .map(|f| (f.name(), [f.extension()]))
+        .collect();
+    filters.push(("Text", [TEXT_EXTENSION]));
+    let mut dialog = FileDialog::new()
+        .set_location(location)
+        .set_filename(&filename);
+    for (name, extensions) in filters.iter() {
+        dialog = dialog.add_filter(name, extensions);
+    }
+
+    match dialog.show_save_single_file() {
+        Ok(Some(path)) if path.extension().is_some_and(|e| e == TEXT_EXTENSION) => {
+            match fs::write(&path, text) {
+                Ok(_) => {
+                    message_success!(ui, tr("保存成功"));
+                }
+                Err(e) => {
+                    message_warn!(ui, format!("{}. {}: {e:?}", tr("保存失败"), tr("原因")));
+                }
+            }
+        }
+        Ok(Some(path)) => {
+            // The dialog does not tell which filter was picked, so the extension decides
+            let (path, format) = match SubtitleFormat::from_path(&path) {
+                Some(format) => (path, format),
+                None => (
+                    path.with_extension(SubtitleFormat::Srt.extension()),
+                    SubtitleFormat::Srt,
+                ),
+            };
+
+            match export::save(output, format, &path) {
+                Ok(_) if text.trim() != output.get_text().trim() => {
+                    message_warn!(ui, tr("已保存，但字幕使用原始分段，不含编辑过的文本"));
+                }
+                Ok(_) => {
+                    message_success!(ui, tr("保存成功"));
+                }
+                Err(e) => {
+                    message_warn!(ui, format!("{}. {}: {e:?}", tr("保存失败"), tr("原因")));
+                }
+            }
+        }
+        Err(e) => {
+            message_warn!(ui, format!("{}. {}: {e:?}", tr("保存失败"), tr("原因")));
+        }
+        _ => (),
+    }
 }
 
-fn inner_start_v2t(model_name: &str, audio_path: &str) -> Result<String> {
-    let models_dir = format!("{}/{}", config::cache_dir(), model_relative_path(0));
+/// Shows a finished transcription in the voice to text panel, as if it had just been
+/// converted, so that it can be read and saved again.
+pub fn show_output(
+    ui: &AppWindow,
+    audio_path: &str,
+    output: transcriber::TranscriberOutput,
+    text: &str,
+) {
+    if IS_CONVERTING.load(Ordering::SeqCst) {
+        message_warn!(ui, tr("有任务正在运行，稍后重试"));
+        return;
+    }
+
+    let info = audio_parser::probe_audio(audio_path)
+        .map(|info| audio_info_text(&info))
+        .unwrap_or_default();
+
+    ui.global::<Store>().set_v2t_audio_path(audio_path.into());
+    ui.global::<Store>().set_v2t_audio_info(info.into());
+    ui.global::<Store>()
+        .set_v2t_detected_language(detected_language_text(&output).into());
+    ui.global::<Store>().set_v2t_text(text.into());
+    ui.global::<Store>().set_v2t_convert_time("".into());
+    *LAST_OUTPUT.lock().unwrap() = Some(output);
+
+    ui.global::<Store>().set_current_panel("v2t".into());
+}
+
+fn init_languages(ui: &AppWindow) {
+    let options = config::transcribe();
+
+    let mut items: Vec<SharedString> = vec!["auto".into()];
+    let mut index = 0;
+    for (code, name) in options::languages() {
+        if options.language.as_deref() == Some(code) {
+            index = items.len();
+        }
+        items.push(format!("{code} ({name})").into());
+    }
+
+    ui.global::<Store>()
+        .set_v2t_languages(ModelRc::new(VecModel::from(items)));
+    ui.global::<Store>().set_v2t_language_index(index as i32);
+    ui.global::<Store>().set_v2t_translate(options.translate);
+}
+
+// "auto" or "zh (chinese)" as shown in the language combobox
+pub fn parse_language(item: &str) -> Option<String> {
+    match item.split_whitespace().next() {
+        Some("auto") | None => None,
+        Some(code) => Some(code.to_string()),
+    }
+}
+
+fn detected_language_text(output: &transcriber::TranscriberOutput) -> String {
+    output
+        .get_language()
+        .map(|l| {
+            format!(
+                "{}: {} {:.0}%",
+                tr("检测到的语言"),
+                l.code,
+                l.probability * 100.0
+            )
+        })
+        .unwrap_or_default()
+}
+
+fn audio_info_text(info: &audio_parser::AudioInfo) -> String {
+    let mut items = vec![];
+
+    if let Some(duration_ms) = info.duration_ms {
+        if let Ok(duration) = util::time::from_timestamp((duration_ms / 1000) as i64, "%H:%M:%S") {
+            items.push(duration);
+        }
+    }
+
+    if let Some(sample_rate) = info.sample_rate {
+        items.push(format!("{sample_rate}Hz"));
+    }
+
+    if let Some(channels) = info.channels {
+        items.push(format!("{channels}ch"));
+    }
+
+    if !info.codec.is_empty() {
+        items.push(info.codec.clone());
+    }
+
+    for (key, value) in info.tags.iter() {
+        if key == "TrackTitle" || key == "Artist" {
+            items.push(value.clone());
+        }
+    }
+
+    items.join(" | ")
+}
 
-    let m = model_handler::ModelHandler::new(model_name, &models_dir)?;
-    let trans = transcriber::Transcriber::new(m)?;
-    let result = trans.transcribe(audio_path, None)?;
-    Ok(result.get_text().to_string())
+async fn inner_start_v2t(
+    model_name: &str,
+    audio_path: &str,
+    options: options::TranscribeOptions,
+    control: JobControl,
+) -> Result<transcriber::TranscriberOutput> {
+    // Loading a model is as blocking as the inference itself
+    let path = model_cache::model_path(model_name);
+    let trans = tokio::task::spawn_blocking(move || model_cache::transcriber(&path)).await??;
+    trans.transcribe_async(audio_path, options, control).await
 }
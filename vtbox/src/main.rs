@@ -10,26 +10,48 @@ extern crate lazy_static;
 
 extern crate transcribe;
 
+mod cli;
 mod config;
+mod db;
 mod logic;
+mod model_cache;
 mod util;
 mod version;
 
-use logic::{about, clipboard, message, model, ok_cancel_dialog, setting, v2t, window};
+use logic::{
+    about, batch, bundle, clipboard, download, history, message, model, ok_cancel_dialog, setting,
+    v2t, window,
+};
 
 use anyhow::Result;
 use chrono::Local;
+use clap::Parser;
 use env_logger::fmt::Color as LColor;
 use log::debug;
 use std::io::Write;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    #[cfg(target_os = "windows")]
+    if std::env::args_os().len() > 1 {
+        attach_console();
+    }
+
     init_logger();
     debug!("start...");
 
+    let cli = cli::Cli::parse();
     config::init();
 
+    if let Some(command) = cli.command {
+        return cli::run(command, cli.json).await;
+    }
+
+    model_cache::preload();
+    if let Err(e) = db::init(&config::db_path()).await {
+        log::warn!("{e:?}");
+    }
+
     let ui = AppWindow::new()?;
 
     logic::util::init(&ui);
@@ -43,7 +65,11 @@ async fn main() -> Result<()> {
     ok_cancel_dialog::init(&ui);
 
     model::init(&ui);
+    download::init(&ui);
+    bundle::init(&ui);
     v2t::init(&ui);
+    batch::init(&ui);
+    history::init(&ui);
 
     ui.run().unwrap();
 
@@ -51,6 +77,23 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+// The GUI subsystem starts without a console, the command line writes to the one of the
+// shell it was started from
+#[cfg(target_os = "windows")]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // Fails when there is no parent console, there is nowhere to write to then
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
 fn init_logger() {
     env_logger::builder()
         .format(|buf, record| {
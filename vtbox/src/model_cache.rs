@@ -0,0 +1,265 @@
+use crate::config;
+use crate::logic::model::{self, model_relative_path};
+use crate::transcribe::{model_handler::ModelHandler, transcriber::Transcriber};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+lazy_static! {
+    // Loaded models, loading a large model takes several seconds
+    static ref CACHE: Mutex<Lru<Arc<Transcriber>>> = Mutex::new(Lru::default());
+
+    // One lock per model path, held while the model loads
+    static ref LOADING: Loading = Loading::default();
+}
+
+/// Locks of the models being loaded, so that concurrent requests of a model load it only once.
+#[derive(Default)]
+struct Loading {
+    locks: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl Loading {
+    /// Returns the cached value of `key`, or loads it with `load` and caches it. Callers of
+    /// the same key wait for the one loading it, callers of other keys are not blocked.
+    fn get_or_load<T: Clone>(
+        &self,
+        cache: &Mutex<Lru<T>>,
+        key: &str,
+        size: u64,
+        modified: Option<SystemTime>,
+        budget: u64,
+        load: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        if let Some(value) = cache.lock().unwrap().get(key, modified) {
+            return Ok(value);
+        }
+
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_default()
+            .clone();
+
+        let result = {
+            let _guard = lock.lock().unwrap();
+
+            // Loaded by another caller while waiting
+            let cached = cache.lock().unwrap().get(key, modified);
+            match cached {
+                Some(value) => Ok(value),
+                None => load().inspect(|value| {
+                    cache
+                        .lock()
+                        .unwrap()
+                        .insert(key, size, modified, value.clone(), budget)
+                }),
+            }
+        };
+
+        // The lock goes with its last user, the others cloned it under the map lock
+        let mut locks = self.locks.lock().unwrap();
+        if Arc::strong_count(&lock) == 2 {
+            locks.remove(key);
+        }
+        result
+    }
+}
+
+/// Entries of the cache, the most recently used one last.
+struct Lru<T> {
+    entries: Vec<Entry<T>>,
+}
+
+struct Entry<T> {
+    key: String,
+    size: u64,
+    modified: Option<SystemTime>,
+    value: T,
+}
+
+impl<T> Default for Lru<T> {
+    fn default() -> Self {
+        Self { entries: vec![] }
+    }
+}
+
+impl<T: Clone> Lru<T> {
+    /// Returns the value of `key` and marks it as the most recently used. An entry loaded from
+    /// a file that changed since is dropped.
+    fn get(&mut self, key: &str, modified: Option<SystemTime>) -> Option<T> {
+        let index = self.entries.iter().position(|e| e.key == key)?;
+        let entry = self.entries.remove(index);
+        if entry.modified != modified {
+            return None;
+        }
+
+        let value = entry.value.clone();
+        self.entries.push(entry);
+        Some(value)
+    }
+
+    /// Inserts `value` and evicts the least recently used entries until the total size is
+    /// within `budget`. The new entry is always kept, even if it is larger than the budget.
+    fn insert(
+        &mut self,
+        key: &str,
+        size: u64,
+        modified: Option<SystemTime>,
+        value: T,
+        budget: u64,
+    ) {
+        self.remove(key);
+        self.entries.push(Entry {
+            key: key.to_string(),
+            size,
+            modified,
+            value,
+        });
+
+        while self.entries.len() > 1 && self.size() > budget {
+            let entry = self.entries.remove(0);
+            log::debug!("evict model {}", entry.key);
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.entries.retain(|e| e.key != key);
+    }
+
+    fn size(&self) -> u64 {
+        self.entries.iter().map(|e| e.size).sum()
+    }
+}
+
+/// Returns the transcriber of the model at `path`, loading the model on first use. Every
+/// transcription creates its own whisper state, so the transcriber can be shared by jobs.
+pub fn transcriber(path: &str) -> Result<Arc<Transcriber>> {
+    // A loaded model takes about as much memory as its file
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified().ok();
+
+    let budget = config::model().memory_budget_mb * 1024 * 1024;
+
+    LOADING.get_or_load(&CACHE, path, metadata.len(), modified, budget, || {
+        Ok(Arc::new(load(path)?))
+    })
+}
+
+/// Path of a model of the V2T panel.
+pub fn model_path(model_name: &str) -> String {
+    format!(
+        "{}/{}/{}",
+        config::cache_dir(),
+        model_relative_path(0),
+        model_name
+    )
+}
+
+/// Drops the model at `path`, running jobs keep their copy until they finish.
+pub fn remove(path: &str) {
+    CACHE.lock().unwrap().remove(path);
+}
+
+/// Loads the last used model in the background if enabled in the config.
+pub fn preload() {
+    let conf = config::model();
+    if !conf.preload || conf.last_used.is_empty() {
+        return;
+    }
+
+    let path = model_path(&conf.last_used);
+    if !Path::new(&path).exists() {
+        return;
+    }
+
+    std::thread::spawn(move || match transcriber(&path) {
+        Ok(_) => log::debug!("preloaded model {path}"),
+        Err(e) => log::warn!("preload model {path} failed: {e:?}"),
+    });
+}
+
+fn load(path: &str) -> Result<Transcriber> {
+    let path = Path::new(path);
+    let (name, dir) = match (path.file_name(), path.parent()) {
+        (Some(name), Some(dir)) => (name.to_string_lossy(), dir.to_string_lossy()),
+        _ => return Err(anyhow!("invalid model path: {}", path.display())),
+    };
+
+    Transcriber::new(ModelHandler::new(&name, &dir)?).map_err(|e| {
+        match model::model_status(0, &name) {
+            "Mismatch" => e.context(format!(
+                "{name} does not match its checksum, download it again"
+            )),
+            _ => e,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru() {
+        let mut lru = Lru::default();
+        lru.insert("a", 40, None, 1, 100);
+        lru.insert("b", 40, None, 2, 100);
+        assert_eq!(lru.get("a", None), Some(1));
+
+        // "b" is the least recently used
+        lru.insert("c", 40, None, 3, 100);
+        assert_eq!(lru.get("b", None), None);
+        assert_eq!(lru.get("a", None), Some(1));
+        assert_eq!(lru.get("c", None), Some(3));
+
+        // Larger than the budget, only the new entry is kept
+        lru.insert("d", 200, None, 4, 100);
+        assert_eq!(lru.size(), 200);
+        assert_eq!(lru.get("d", None), Some(4));
+
+        // The file changed since it was loaded
+        assert_eq!(lru.get("d", Some(SystemTime::now())), None);
+        assert_eq!(lru.size(), 0);
+    }
+
+    #[test]
+    fn test_get_or_load() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = Mutex::new(Lru::default());
+        let loading = Loading::default();
+        let loads = AtomicUsize::new(0);
+
+        // Concurrent cold requests of a model load it once
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let value = loading.get_or_load(&cache, "a", 40, None, 100, || {
+                        loads.fetch_add(1, Ordering::SeqCst);
+                        std::thread::sleep(std::time::Duration::from_millis(100));
+                        Ok(1)
+                    });
+                    assert_eq!(value.unwrap(), 1);
+                });
+            }
+        });
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert!(loading.locks.lock().unwrap().is_empty());
+
+        // A failed load is not cached
+        let value = loading.get_or_load(&cache, "b", 40, None, 100, || Err(anyhow!("failed")));
+        assert!(value.is_err());
+        assert_eq!(
+            loading
+                .get_or_load(&cache, "b", 40, None, 100, || Ok(2))
+                .unwrap(),
+            2
+        );
+    }
+}
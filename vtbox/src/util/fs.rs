@@ -1,6 +1,7 @@
 use anyhow::Result;
 use std::path::{Path, PathBuf};
 use std::{env, fs, io};
+use transcribe::manifest;
 
 const G: u64 = 1024 * 1024 * 1024;
 const M: u64 = 1024 * 1024;
@@ -87,6 +88,39 @@ pub fn pretty_size(size: u64) -> String {
     }
 }
 
+/// Creates an empty file at `path`, or at "<stem>-1.<ext>", "<stem>-2.<ext>"... when it is
+/// taken, so that an existing file is never overwritten. Returns the path of the new file.
+pub fn create_new_file(path: &Path) -> io::Result<PathBuf> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = match path.extension() {
+        Some(extension) => format!(".{}", extension.to_string_lossy()),
+        None => String::default(),
+    };
+
+    for n in 0.. {
+        let candidate = match n {
+            0 => path.to_path_buf(),
+            n => path.with_file_name(format!("{stem}-{n}{extension}")),
+        };
+
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(_) => return Ok(candidate),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!()
+}
+
+/// Hex SHA-256 of the file, read in chunks so that large media files are fine.
+pub fn sha256(path: &str) -> Result<String> {
+    manifest::sha256_file(Path::new(path))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -144,4 +178,35 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_create_new_file() -> Result<()> {
+        let dir = env::temp_dir().join("vtbox-test-create-new-file");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join("a.srt");
+        assert_eq!(create_new_file(&path)?, path);
+        assert_eq!(create_new_file(&path)?, dir.join("a-1.srt"));
+        assert_eq!(create_new_file(&path)?, dir.join("a-2.srt"));
+        assert_eq!(create_new_file(&dir.join("b"))?, dir.join("b"));
+        assert_eq!(create_new_file(&dir.join("b"))?, dir.join("b-1"));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_sha256() -> Result<()> {
+        let path = env::temp_dir().join("vtbox-test-sha256");
+        fs::write(&path, "abc")?;
+        let hash = sha256(path.to_str().unwrap())?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(
+            hash,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        Ok(())
+    }
 }
@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, Local, Utc};
+use chrono::{DateTime, Local, TimeZone, Utc};
 
 pub fn local_now(format: &str) -> String {
     return Local::now().format(format).to_string();
@@ -10,3 +10,11 @@ pub fn from_timestamp(timestamp: i64, format: &str) -> Result<String> {
         .with_context(|| format!("invalid timestamp: {timestamp}"))?;
     Ok(dt.format(format).to_string())
 }
+
+pub fn local_from_timestamp(timestamp: i64, format: &str) -> Result<String> {
+    let dt = Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .with_context(|| format!("invalid timestamp: {timestamp}"))?;
+    Ok(dt.format(format).to_string())
+}
@@ -19,6 +19,25 @@ pub fn tr(text: &str) -> String {
     items.insert("清空成功", "Delete success");
     items.insert("保存失败", "Save failed");
     items.insert("保存成功", "Save success");
+    items.insert("没有可保存的内容", "Nothing to save");
+    items.insert(
+        "已保存，但字幕使用原始分段，不含编辑过的文本",
+        "Saved, but the subtitles use the original segments, without the edits",
+    );
+    items.insert("检测到的语言", "Detected language");
+    items.insert("正在取消...", "Cancelling...");
+    items.insert("已取消转换", "Transcription cancelled");
+    items.insert("排队中", "Queued");
+    items.insert("转换中", "Running");
+    items.insert("已完成", "Done");
+    items.insert("失败", "Failed");
+    items.insert("已取消", "Cancelled");
+    items.insert("没有排队的任务", "No queued jobs");
+    items.insert("批量转换完成", "Batch finished");
+    items.insert("记录不存在", "Record not found");
+    items.insert("请先选择一条记录", "Please select a record");
+    items.insert("已编辑", "Edited");
+    items.insert("翻译为英文", "Translate to English");
     items.insert("重置成功", "Reset success");
     items.insert("刷新成功", "Flush success");
     items.insert("发送失败", "Send failed");
@@ -26,6 +45,38 @@ pub fn tr(text: &str) -> String {
     items.insert("下载失败", "Download failed");
     items.insert("导入失败", "Import failed");
     items.insert("导入成功", "Import success");
+    items.insert("正在校验...", "Verifying...");
+    items.insert("校验成功", "Verify success");
+    items.insert("校验失败", "Verify failed");
+    items.insert("模型文件已损坏", "Corrupt model files");
+    items.insert("没有校验和，无法校验", "No checksum to verify against");
+    items.insert("没有可信的校验和", "No trusted checksum");
+    items.insert("全部", "All");
+    items.insert("已加入下载队列", "Added to the download queue");
+    items.insert("已取消下载", "Download cancelled");
+    items.insert("没有正在进行的下载", "No download in progress");
+    items.insert("没有暂停的下载", "No paused download");
+    items.insert("请先选择一个模型", "Please select a model first");
+    items.insert("系列", "Family");
+    items.insert("语言", "Languages");
+    items.insert("量化", "Quantization");
+    items.insert("未下载", "Not downloaded");
+    items.insert("路径", "Path");
+    items.insert("大小", "Size");
+    items.insert("格式", "Format");
+    items.insert("类型", "Type");
+    items.insert("架构", "Architecture");
+    items.insert(
+        "whisper.cpp 无法加载 GGUF 模型",
+        "whisper.cpp can't load GGUF models",
+    );
+    items.insert("无法解析模型文件头", "Can't parse the model header");
+    items.insert("没有可导出的模型", "No model to export");
+    items.insert("正在导出...", "Exporting...");
+    items.insert("导出成功", "Export success");
+    items.insert("导出失败", "Export failed");
+    items.insert("正在导入...", "Importing...");
+    items.insert("重命名", "Renamed");
     items.insert("加载失败", "Load failed");
     items.insert("密码错误", "Password Invalid");
     items.insert("正在重试...", "Retrying...");
@@ -44,6 +95,11 @@ pub fn tr(text: &str) -> String {
     items.insert("发送交易成功", "Send transaction success");
     items.insert("发送交易失败", "Send transaction failed");
     items.insert("打开文件失败", "Open file failed");
+    items.insert("无法识别的音频文件", "Unrecognized audio file");
+    items.insert(
+        "音频较长，转换需要较长时间",
+        "The audio is long, transcribing will take a while",
+    );
     items.insert("非法交易", "Invalid transaction");
     items.insert(
         "内部错误，请刷新列表",
@@ -56,7 +112,10 @@ pub fn tr(text: &str) -> String {
     items.insert("转换成功", "transcribe success");
     items.insert("转换失败", "transcribe failed");
     items.insert("请先下载模型", "Please download the models");
-    items.insert("有任务正在运行，稍后重试", "Other task in running, try it latter");
+    items.insert(
+        "有任务正在运行，稍后重试",
+        "Other task in running, try it latter",
+    );
     items.insert("刷新...", "Flush...");
     items.insert("在线", "Online");
     items.insert("正忙", "Busy");